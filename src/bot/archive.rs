@@ -0,0 +1,287 @@
+// zips of schematics and maps, eg an exported schematic folder.
+use super::schematic::{self, Schem};
+use super::{Msg, Type, dupes, emojis, repos::Repo, strip_colors};
+use crate::emoji;
+use anyhow::Result;
+use mindus::data::DataRead;
+use mindus::{Map, Schematic};
+use poise::serenity_prelude::*;
+use std::fmt::Write;
+use std::io::Read;
+use std::time::Duration;
+
+/// At most this many files are read out of an archive.
+const LIMIT: usize = 100;
+/// Files larger than this (uncompressed) are skipped.
+const SIZE: u64 = 8 << 20;
+/// Side of a contact sheet cell, in pixels.
+const CELL: usize = 160;
+
+pub fn is(a: &Attachment) -> bool {
+    a.filename.ends_with(".zip")
+}
+
+/// (path, contents) of every `.msch` and `.msav` in the archive.
+fn unpack(b: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut z = zip::ZipArchive::new(std::io::Cursor::new(b))?;
+    let mut v = vec![];
+    for i in 0..z.len() {
+        if v.len() == LIMIT {
+            break;
+        }
+        let f = z.by_index(i)?;
+        let name = f.name().to_string();
+        if !f.is_file() || f.size() > SIZE || !(name.ends_with(".msch") || name.ends_with(".msav"))
+        {
+            continue;
+        }
+        let mut buf = Vec::with_capacity(f.size() as usize);
+        // the size is only what the archive claims
+        f.take(SIZE).read_to_end(&mut buf)?;
+        v.push((name, buf));
+    }
+    Ok(v)
+}
+
+pub async fn download(a: &Attachment) -> Result<Vec<(String, Vec<u8>)>> {
+    let b = a.download().await?;
+    tokio::task::spawn_blocking(move || unpack(&b)).await?
+}
+
+/// Shrinks a render to fit a contact sheet cell, keeping the aspect ratio.
+/// Done as soon as it's rendered, as a hundred full size maps dont fit in memory.
+fn thumb(i: fimg::Image<Vec<u8>, 3>) -> fimg::Image<Vec<u8>, 3> {
+    let (iw, ih) = (i.width() as usize, i.height() as usize);
+    let scale = (CELL as f32 / iw.max(ih).max(1) as f32).min(1.0);
+    let (tw, th) = (
+        ((iw as f32 * scale) as usize).max(1),
+        ((ih as f32 * scale) as usize).max(1),
+    );
+    let src = i.take_buffer();
+    let mut buf = vec![0; tw * th * 3];
+    for y in 0..th {
+        for x in 0..tw {
+            let s = ((y * ih / th) * iw + x * iw / tw) * 3;
+            let d = (y * tw + x) * 3;
+            buf[d..d + 3].copy_from_slice(&src[s..s + 3]);
+        }
+    }
+    fimg::Image::build(tw as u32, th as u32).buf(buf)
+}
+
+/// Lays the thumbnails out in a grid, numbered.
+fn sheet(renders: Vec<fimg::Image<Vec<u8>, 3>>) -> Vec<u8> {
+    let cols = (renders.len() as f32).sqrt().ceil().max(1.0) as usize;
+    let rows = renders.len().div_ceil(cols);
+    let pad = 8;
+    let (w, h) = (cols * (CELL + pad) + pad, rows * (CELL + pad) + pad);
+    let mut buf = vec![0x1e; w * h * 3];
+    for (n, i) in renders.into_iter().enumerate() {
+        let (tw, th) = (i.width() as usize, i.height() as usize);
+        let x0 = pad + (n % cols) * (CELL + pad) + (CELL - tw) / 2;
+        let y0 = pad + (n / cols) * (CELL + pad) + (CELL - th) / 2;
+        let src = i.take_buffer();
+        for (y, row) in src.chunks_exact(tw * 3).enumerate() {
+            let d = ((y0 + y) * w + x0) * 3;
+            buf[d..d + row.len()].copy_from_slice(row);
+        }
+        let (cx, cy) = (
+            pad + (n % cols) * (CELL + pad),
+            pad + (n / cols) * (CELL + pad),
+        );
+        super::font::number(n + 1, (cx as i64, cy as i64), [0x1e; 3], |x, y, c| {
+            if (0..w as i64).contains(&x) && (0..h as i64).contains(&y) {
+                let d = (y as usize * w + x as usize) * 3;
+                buf[d..d + 3].copy_from_slice(&c);
+            }
+        });
+    }
+    super::png(fimg::Image::build(w as u32, h as u32).buf(buf))
+}
+
+enum Entry {
+    Schem(Schem),
+    /// the name, as maps are only shown
+    Map(String),
+}
+
+/// Renders every schematic and map in the message's archives, offering to add the schematics to the channel's repo.
+pub async fn with(
+    c: &serenity::client::Context,
+    m: &Message,
+    who: &str,
+    labels: Option<Type>,
+    repo: Option<(&'static str, &Repo)>,
+) -> Result<()> {
+    use emoji::named::*;
+    let t = m.channel_id.start_typing(&c.http);
+    let mut files = vec![];
+    for a in m.attachments.iter().filter(|a| is(a)) {
+        match download(a).await {
+            Ok(x) => files.extend(x),
+            Err(e) => {
+                m.reply(c, format!("{CANCEL} couldnt read `{}`: {e}", a.filename))
+                    .await?;
+                return Ok(());
+            }
+        }
+    }
+    let (entries, png) = tokio::task::spawn_blocking(move || {
+        let mut entries = vec![];
+        let mut renders = vec![];
+        for (name, b) in files {
+            if name.ends_with(".msch") {
+                let Ok(s) = Schematic::deserialize(&mut DataRead::new(&b)) else {
+                    continue;
+                };
+                renders.push(thumb(s.render()));
+                entries.push(Entry::Schem(Schem { schem: s }));
+            } else if let Ok(map) = Map::deserialize(&mut DataRead::new(&b)) {
+                let name = map
+                    .tags
+                    .get("name")
+                    .or(map.tags.get("mapname"))
+                    .cloned()
+                    .unwrap_or(name);
+                renders.push(thumb(map.render()));
+                entries.push(Entry::Map(name));
+            }
+        }
+        let png = (!renders.is_empty()).then(|| sheet(renders));
+        (entries, png)
+    })
+    .await?;
+    let Some(png) = png else {
+        t.stop();
+        m.reply(c, format!("{CANCEL} no schematics or maps in there"))
+            .await?;
+        return Ok(());
+    };
+    super::data::push_j(serde_json::json! {{
+    "locale": m.author.locale.as_deref().unwrap_or("no locale"),
+    "name":  m.author.name,
+    "id": m.author.id,
+    "cname": "archive input",
+    "guild": m.guild_id.map_or(0,|x|x.get()),
+    "channel": m.channel_id.get(),
+    }});
+    let mut list = String::new();
+    for (n, e) in (1..).zip(&entries) {
+        let line = match e {
+            Entry::Schem(s) => format!(
+                "{n}. {}\n",
+                emoji::mindustry::to_discord(&strip_colors(s.tags.get("name").unwrap()))
+            ),
+            Entry::Map(name) => format!("{n}. {MAP} {}\n", strip_colors(name)),
+        };
+        if list.len() + line.len() > 4000 {
+            write!(list, "…and {} more", entries.len() - n + 1).unwrap();
+            break;
+        }
+        list.push_str(&line);
+    }
+    let schems = entries
+        .into_iter()
+        .filter_map(|x| match x {
+            Entry::Schem(s) => Some(s),
+            Entry::Map(_) => None,
+        })
+        .collect::<Vec<_>>();
+    let mut msg = CreateMessage::new()
+        .reference_message(m)
+        .add_file(CreateAttachment::bytes(png, "sheet.png"))
+        .embed(
+            CreateEmbed::new()
+                .author(CreateEmbedAuthor::new(who).icon_url(m.author.face()))
+                .description(list)
+                .image("attachment://sheet.png")
+                .color(super::SUCCESS),
+        );
+    let add = repo.filter(|_| !schems.is_empty());
+    if add.is_some() {
+        msg = msg.button(
+            CreateButton::new("add")
+                .label(format!("add {} to the repo", schems.len()))
+                .style(ButtonStyle::Success),
+        );
+    }
+    t.stop();
+    let mut sheet = m.channel_id.send_message(c, msg).await?;
+    let Some((dir, repo)) = add else {
+        return Ok(());
+    };
+    // only the uploader may add them
+    let Some(i) = ComponentInteractionCollector::new(c)
+        .message_id(sheet.id)
+        .author_id(m.author.id)
+        .timeout(Duration::from_secs(60 * 10))
+        .next()
+        .await
+    else {
+        sheet.edit(c, EditMessage::new().components(vec![])).await?;
+        return Ok(());
+    };
+    i.create_response(
+        c,
+        CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new().components(vec![]),
+        ),
+    )
+    .await?;
+    let (mut added, mut skipped) = (0, 0);
+    for mut s in schems {
+        schematic::label(&mut s, &labels);
+        if let Some(dupes::Dupe::Exact(_)) = dupes::find(repo, &s, 0) {
+            skipped += 1;
+            continue;
+        }
+        let (post, _, mut v) = schematic::send(
+            Msg {
+                avatar: m.author.face(),
+                author: who.to_string(),
+                locale: m
+                    .author
+                    .locale
+                    .clone()
+                    .unwrap_or("unknown locale".to_string()),
+                author_id: m.author.id.get(),
+                guild: m.guild_id.map_or(0, Into::into),
+                content: String::new(),
+                channel: m.channel_id,
+                attachments: vec![],
+            },
+            c,
+            vec![s],
+        )
+        .await?;
+        let s = v.pop().unwrap();
+        dupes::insert(
+            repo,
+            &s,
+            dupes::Post {
+                channel: post.channel_id.get(),
+                message: post.id.get(),
+            },
+        );
+        repo.own()
+            .await
+            .insert(post.id.get(), (m.author.name.clone(), m.author.id.get()));
+        repo.write(dir, post.id, s);
+        post.react(c, emojis::get!(MERGE)).await?;
+        added += 1;
+    }
+    if added != 0 {
+        repo.commit(
+            who,
+            m.author.id,
+            &format!("add {added} schematics from an archive"),
+        );
+        repo.push();
+    }
+    let mut r = format!("{OK} added {added} schematics");
+    if skipped != 0 {
+        write!(r, ", skipping {skipped} already in the repo").unwrap();
+    }
+    sheet.reply(c, r).await?;
+    Ok(())
+}
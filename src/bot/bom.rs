@@ -0,0 +1,138 @@
+use super::schematic::{self, Schem};
+use super::{Context, SUCCESS, strip_colors};
+use crate::emoji;
+use anyhow::Result;
+use mindus::Schematic;
+use mindus::block::Block;
+use mindus::item::Type;
+use poise::{CreateReply, serenity_prelude::*};
+use std::fmt::Write;
+
+pub struct Line {
+    pub block: &'static Block,
+    pub count: u32,
+    pub each: Vec<(Type, u32)>,
+}
+
+impl Line {
+    pub fn subtotal(&self) -> impl Iterator<Item = (Type, u32)> + '_ {
+        self.each.iter().map(|&(i, n)| (i, n * self.count))
+    }
+}
+
+/// Bill of materials: every block type in the schematic, most numerous first.
+pub fn lines(s: &Schematic) -> Vec<Line> {
+    let mut lines: Vec<Line> = vec![];
+    for (_, p) in s.block_iter() {
+        match lines.iter_mut().find(|l| l.block == p.block) {
+            Some(l) => l.count += 1,
+            None => lines.push(Line {
+                block: p.block,
+                count: 1,
+                each: p
+                    .block
+                    .get_build_cost()
+                    .map(|c| c.iter().filter(|&(_, n)| n != 0).collect())
+                    .unwrap_or_default(),
+            }),
+        }
+    }
+    lines.sort_by(|a, b| b.count.cmp(&a.count).then(a.block.name().cmp(b.block.name())));
+    lines
+}
+
+fn items(of: impl Iterator<Item = (Type, u32)>) -> String {
+    let mut s = String::new();
+    for (i, n) in of {
+        write!(s, "{} {n} ", emoji::mindustry::item(i)).unwrap();
+    }
+    s
+}
+
+pub fn csv(lines: &[Line]) -> String {
+    let mut s = String::from("block,count,item,each,subtotal\n");
+    for l in lines {
+        if l.each.is_empty() {
+            writeln!(s, "{},{},,0,0", l.block.name(), l.count).unwrap();
+        }
+        for &(i, n) in &l.each {
+            writeln!(s, "{},{},{i},{n},{}", l.block.name(), l.count, n * l.count).unwrap();
+        }
+    }
+    s
+}
+
+pub fn json(lines: &[Line]) -> String {
+    let obj = |of: &mut dyn Iterator<Item = (Type, u32)>| {
+        of.map(|(i, n)| (i.to_string(), serde_json::json!(n)))
+            .collect::<serde_json::Map<_, _>>()
+    };
+    serde_json::to_string_pretty(
+        &lines
+            .iter()
+            .map(|l| {
+                serde_json::json! {{
+                    "block": l.block.name(),
+                    "count": l.count,
+                    "each": obj(&mut l.each.iter().copied()),
+                    "subtotal": obj(&mut l.subtotal()),
+                }}
+            })
+            .collect::<Vec<_>>(),
+    )
+    .unwrap()
+}
+
+#[poise::command(
+    slash_command,
+    install_context = "User|Guild",
+    interaction_context = "Guild|BotDm|PrivateChannel"
+)]
+/// Break a schematic's cost down per block, with csv and json exports.
+pub async fn bom(
+    c: Context<'_>,
+    #[description = "schematic, base64"] s: Option<String>,
+    #[description = "schematic, msch"] file: Option<Attachment>,
+) -> Result<()> {
+    super::log(&c);
+    c.defer().await?;
+    let Some(Schem { schem: s }) = schematic::from_either(s, file).await? else {
+        c.reply("no schem!").await?;
+        return Ok(());
+    };
+    let lines = lines(&s);
+    let mut table = String::new();
+    for l in &lines {
+        let line = format!(
+            "**{}**× `{}` — {}— **{}**\n",
+            l.count,
+            l.block.name(),
+            items(l.each.iter().copied()),
+            items(l.subtotal())
+        );
+        // embed descriptions cap out at 4096
+        if table.len() + line.len() > 4000 {
+            table.push_str("…see the attachments for the rest");
+            break;
+        }
+        table.push_str(&line);
+    }
+    let name = emoji::mindustry::to_discord(&strip_colors(s.tags.get("name").unwrap()));
+    let file = schematic::file_name(&s);
+    poise::send_reply(
+        c,
+        CreateReply::default()
+            .embed(
+                CreateEmbed::new()
+                    .title(name)
+                    .description(table)
+                    .field("total", schematic::cost(&s), false)
+                    .footer(CreateEmbedFooter::new("count × block — each — subtotal"))
+                    .color(SUCCESS),
+            )
+            .attachment(CreateAttachment::bytes(csv(&lines), format!("{file}.csv")))
+            .attachment(CreateAttachment::bytes(json(&lines), format!("{file}.json"))),
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,312 @@
+// crash reports, kept on disk until someone gets around to them.
+use super::{Context, OWNER, SUCCESS};
+use anyhow::Result;
+use mindus::{Map, Schematic, data::DataRead};
+use poise::{CreateReply, serenity_prelude::*};
+use serde_json::{Value, json};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DIR: &str = "crashes";
+
+/// (message, backtrace) of recent panics, as a [`tokio::task::JoinError`] doesnt carry a backtrace.
+static PANICS: Mutex<Vec<(String, String)>> = Mutex::new(vec![]);
+/// Panics kept around for their report.
+const KEEP: usize = 16;
+
+/// Keeps the default hook, but also remembers the backtrace.
+pub fn hook() {
+    let default = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let bt = std::backtrace::Backtrace::force_capture();
+        if let Ok(mut p) = PANICS.lock() {
+            if p.len() == KEEP {
+                p.remove(0);
+            }
+            p.push((message(info.payload()), format!("{info}\n{bt}")));
+        }
+        default(info)
+    }));
+}
+
+fn message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|x| x.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "panic".to_string())
+}
+
+/// The backtrace of the panic that said `message`.
+pub fn backtrace(message: &str) -> Option<String> {
+    let mut p = PANICS.lock().ok()?;
+    let i = p.iter().rposition(|(m, _)| m == message)?;
+    Some(p.remove(i).1)
+}
+
+/// The error and, if it panicked, the backtrace of a failed task.
+pub fn panicked(e: tokio::task::JoinError) -> (String, Option<String>) {
+    match e.try_into_panic() {
+        Ok(payload) => {
+            let m = message(&*payload);
+            let bt = backtrace(&m);
+            (m, bt)
+        }
+        Err(e) => (e.to_string(), None),
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Kind {
+    Map,
+    Schematic,
+}
+
+impl Kind {
+    fn ext(self) -> &'static str {
+        match self {
+            Self::Map => "msav",
+            Self::Schematic => "msch",
+        }
+    }
+}
+
+/// Returned once a failure has been reported, so [`super::on_error`] doesnt report it twice.
+#[derive(Debug)]
+pub struct Filed(pub u64);
+
+impl std::fmt::Display for Filed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "crash #{}", self.0)
+    }
+}
+
+impl std::error::Error for Filed {}
+
+fn path(id: u64) -> PathBuf {
+    Path::new(DIR).join(id.to_string())
+}
+
+fn now() -> Duration {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap()
+}
+
+/// Stores a report, returning its id.
+/// Without a `backtrace`, one is taken here.
+pub fn file(
+    input: Option<(Kind, &[u8])>,
+    error: &str,
+    backtrace: Option<String>,
+    guild: String,
+    command: &str,
+) -> u64 {
+    let backtrace =
+        backtrace.unwrap_or_else(|| std::backtrace::Backtrace::force_capture().to_string());
+    // ids are the time in ms, bumped until free
+    let mut id = now().as_millis() as u64;
+    while path(id).exists() {
+        id += 1;
+    }
+    let report = json! {{
+        "kind": input.map(|(k, _)| k.ext()),
+        "error": error,
+        "backtrace": backtrace,
+        "guild": guild,
+        "command": command,
+        "at": now().as_secs(),
+        "resolved": false,
+    }};
+    // the id is still worth giving out; the log has the rest
+    if let Err(e) = std::fs::create_dir_all(path(id))
+        .and_then(|_| match input {
+            Some((k, b)) => std::fs::write(path(id).join(format!("input.{}", k.ext())), b),
+            None => Ok(()),
+        })
+        .and_then(|_| write(id, &report))
+    {
+        println!("couldnt store crash #{id}: {e}");
+    }
+    println!("filed crash #{id}: {error}");
+    id
+}
+
+fn write(id: u64, v: &Value) -> std::io::Result<()> {
+    std::fs::write(
+        path(id).join("report.json"),
+        serde_json::to_string_pretty(v).unwrap(),
+    )
+}
+
+fn read(id: u64) -> Option<Value> {
+    serde_json::from_str(&std::fs::read_to_string(path(id).join("report.json")).ok()?).ok()
+}
+
+/// Fills in what wasnt known when the report was filed.
+pub fn annotate(id: u64, guild: String, command: &str) {
+    let Some(mut v) = read(id) else { return };
+    v["guild"] = guild.into();
+    v["command"] = command.into();
+    _ = write(id, &v);
+}
+
+fn input(id: u64, v: &Value) -> Option<(Kind, Vec<u8>)> {
+    let k = match v["kind"].as_str()? {
+        "msav" => Kind::Map,
+        _ => Kind::Schematic,
+    };
+    Some((
+        k,
+        std::fs::read(path(id).join(format!("input.{}", k.ext()))).ok()?,
+    ))
+}
+
+/// Where a command or message came from, for reports.
+pub fn guild(c: &serenity::client::Context, guild: Option<GuildId>, author: &User) -> String {
+    guild.map_or(format!("dms with {}", author.name), |g| {
+        c.cache
+            .guild(g)
+            .map_or(g.get().to_string(), |g| g.name.clone())
+    })
+}
+
+macro_rules! owner {
+    ($c:expr) => {
+        if $c.author().id != OWNER {
+            poise::say_reply($c, "access denied. this incident will be reported").await?;
+            return Ok(());
+        }
+    };
+}
+
+#[poise::command(slash_command)]
+/// List the unresolved crash reports.
+pub async fn crash_list(
+    c: Context<'_>,
+    #[description = "include resolved reports"] all: Option<bool>,
+) -> Result<()> {
+    owner!(c);
+    let mut ids = std::fs::read_dir(DIR)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|x| x.file_name().to_str()?.parse::<u64>().ok())
+        .collect::<Vec<_>>();
+    ids.sort_unstable();
+    let mut list = String::new();
+    for id in ids.into_iter().rev() {
+        let Some(v) = read(id) else { continue };
+        if v["resolved"].as_bool() == Some(true) && all != Some(true) {
+            continue;
+        }
+        let line = format!(
+            "`{id}` <t:{}:R> `{}` in {}{}: {}\n",
+            v["at"].as_u64().unwrap_or(0),
+            v["command"].as_str().unwrap_or("?"),
+            v["guild"].as_str().unwrap_or("?"),
+            if v["resolved"].as_bool() == Some(true) {
+                " (resolved)"
+            } else {
+                ""
+            },
+            v["error"]
+                .as_str()
+                .unwrap_or("?")
+                .lines()
+                .next()
+                .unwrap_or("")
+                .chars()
+                .take(100)
+                .collect::<String>(),
+        );
+        if list.len() + line.len() > 4000 {
+            break;
+        }
+        list.push_str(&line);
+    }
+    if list.is_empty() {
+        list = "no crashes".to_string();
+    }
+    poise::send_reply(
+        c,
+        CreateReply::default().embed(
+            CreateEmbed::new()
+                .title("crash reports")
+                .description(list)
+                .color(SUCCESS),
+        ),
+    )
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command)]
+/// Run a crash report's input again, to see if it's been fixed.
+pub async fn crash_rerun(c: Context<'_>, #[description = "report id"] id: u64) -> Result<()> {
+    use crate::emoji::named::*;
+    owner!(c);
+    let Some(v) = read(id) else {
+        c.say(format!("{CANCEL} no crash #{id}")).await?;
+        return Ok(());
+    };
+    let Some((k, b)) = input(id, &v) else {
+        c.say(format!("{CANCEL} crash #{id} has no input to rerun"))
+            .await?;
+        return Ok(());
+    };
+    c.defer().await?;
+    let file = CreateAttachment::bytes(b.clone(), format!("input.{}", k.ext()));
+    let out = match k {
+        Kind::Map => {
+            let then = std::time::Instant::now();
+            match tokio::task::spawn_blocking(move || {
+                Map::deserialize(&mut DataRead::new(&b)).map_err(|e| format!("{e:?}"))
+            })
+            .await
+            {
+                Err(e) => Err(e.to_string()),
+                Ok(Err(e)) => Err(e),
+                Ok(Ok(m)) => super::map::embed(m, then.elapsed(), super::map::Options::default())
+                    .await
+                    .map(|(a, _)| a)
+                    .map_err(|e| e.to_string()),
+            }
+        }
+        Kind::Schematic => match tokio::task::spawn_blocking(move || {
+            Schematic::deserialize(&mut DataRead::new(&b)).map(|s| super::schematic::to_png(&s))
+        })
+        .await
+        {
+            Err(e) => Err(e.to_string()),
+            Ok(Err(e)) => Err(e.to_string()),
+            Ok(Ok(png)) => Ok(CreateAttachment::bytes(png, "image.png")),
+        },
+    };
+    let r = CreateReply::default().attachment(file);
+    poise::send_reply(
+        c,
+        match out {
+            Ok(a) => r
+                .content(format!("{OK} crash #{id} no longer fails"))
+                .attachment(a),
+            Err(e) => r.content(format!("{CANCEL} crash #{id} still fails: `{e}`")),
+        },
+    )
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command)]
+/// Mark a crash report as resolved.
+pub async fn crash_resolve(c: Context<'_>, #[description = "report id"] id: u64) -> Result<()> {
+    use crate::emoji::named::*;
+    owner!(c);
+    let Some(mut v) = read(id) else {
+        c.say(format!("{CANCEL} no crash #{id}")).await?;
+        return Ok(());
+    };
+    v["resolved"] = true.into();
+    write(id, &v)?;
+    c.say(format!("{OK} resolved crash #{id}")).await?;
+    Ok(())
+}
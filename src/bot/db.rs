@@ -15,6 +15,9 @@ fn cfg() -> kv::Config {
 }
 static DB: LazyLock<Store> = LazyLock::new(|| Store::new(cfg()).unwrap());
 static BU: LazyLock<Bucket<Integer, Vec<u8>>> = LazyLock::new(|| DB.bucket(None).unwrap());
+// channels that lint submissions
+static LINT: LazyLock<Bucket<Integer, Vec<u8>>> =
+    LazyLock::new(|| DB.bucket(Some("lint")).unwrap());
 
 pub fn set(k: u64, v: u64) {
     BU.set(&k.into(), &v.to_le_bytes().to_vec()).unwrap();
@@ -29,6 +32,24 @@ pub fn remove(k: u64) -> Option<u64> {
             BU.flush().unwrap();
         })
 }
+pub fn lints(ch: u64) -> bool {
+    LINT.contains(&ch.into()).unwrap_or(false)
+}
+/// returns the new state
+pub fn toggle_lint(ch: u64) -> bool {
+    let on = if lints(ch) {
+        LINT.remove(&ch.into()).unwrap();
+        false
+    } else {
+        LINT.set(&ch.into(), &vec![]).unwrap();
+        true
+    };
+    LINT.flush().unwrap();
+    on
+}
+pub fn store() -> &'static Store {
+    &DB
+}
 pub fn sz() -> f32 {
     DB.size_on_disk().unwrap() as f32 / (1 << 20) as f32
 }
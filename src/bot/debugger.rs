@@ -0,0 +1,260 @@
+// stepping through mlog an instruction at a time, with buttons.
+use super::logic;
+use super::world::{self, Env};
+use super::{Context, SUCCESS};
+use anyhow::Result;
+use lemu::Executor;
+use poise::{CreateReply, serenity_prelude::*};
+use std::fmt::Write;
+use std::time::Duration;
+
+/// The buttons stop working this long after the last press.
+const IDLE: Duration = Duration::from_secs(5 * 60);
+/// Instructions shown either side of the current one.
+const AROUND: usize = 6;
+/// Running to the next print gives up after this many instructions, so a press is answered quickly.
+const LIMIT: usize = 5_000;
+/// Iterations before the program counts as done: the most `}eval` allows.
+const ITERATIONS: usize = 50;
+
+/// (source line, instruction as written) for every instruction, indexed by its counter.
+/// Labels, comments and blank lines take no place in the counter; every other line does.
+fn listing(code: &str) -> Vec<(usize, String)> {
+    code.lines()
+        .enumerate()
+        .map(|(n, l)| (n + 1, world::tokens(l)))
+        .filter(|(_, t)| !t.is_empty() && !(t.len() == 1 && t[0].ends_with(':')))
+        .map(|(n, t)| (n, t.join(" ").replace('`', "\u{200b}`")))
+        .collect()
+}
+
+fn embed(v: &Executor<'_, Vec<u8>>, listing: &[(usize, String)], steps: usize) -> CreateEmbed {
+    let at = v.counter();
+    let mut code = String::new();
+    for (i, (line, l)) in listing
+        .iter()
+        .enumerate()
+        .skip(at.saturating_sub(AROUND))
+        .take(AROUND * 2 + 1)
+    {
+        if i == at && !v.done() {
+            writeln!(code, "\x1b[1;33m>{line:3} {l}\x1b[0m").unwrap();
+        } else {
+            writeln!(code, " {line:3} {l}").unwrap();
+        }
+    }
+    let mut vars = String::new();
+    // a field holds 1024 characters
+    for l in logic::table(&logic::variables(v)).lines() {
+        if vars.len() + l.len() > 1000 {
+            vars.push('…');
+            break;
+        }
+        writeln!(vars, "{l}").unwrap();
+    }
+    let mut footer = format!("{steps} steps, iteration {}", v.iterations() + 1);
+    if v.done() {
+        footer.push_str(", done");
+    }
+    CreateEmbed::new()
+        .description(format!("```ansi\n{code}```"))
+        .field("variables", format!("```\n{vars}```"), false)
+        .footer(CreateEmbedFooter::new(footer))
+        .color(SUCCESS)
+}
+
+fn buttons(done: bool) -> Vec<CreateActionRow> {
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new("step").label("step").disabled(done),
+        CreateButton::new("step10").label("step ×10").disabled(done),
+        CreateButton::new("next")
+            .label("run to print / drawflush")
+            .disabled(done),
+        CreateButton::new("reset")
+            .label("reset")
+            .style(ButtonStyle::Danger),
+    ])]
+}
+
+/// What a button asks of the executor.
+enum Press {
+    Step(usize),
+    /// run to the next print or drawflush
+    Next,
+    Reset,
+}
+
+/// (embed, done, note)
+type View = (CreateEmbed, bool, String);
+
+/// Owns the executor for the whole session, on a blocking thread: it borrows the code, and a press may run thousands of instructions.
+fn session(
+    code: String,
+    presses: std::sync::mpsc::Receiver<Press>,
+    views: tokio::sync::mpsc::UnboundedSender<Result<View, String>>,
+) {
+    // no rewriting, so the listing lines up with what lemu runs
+    let env = Env::default();
+    let new = || logic::executor(&code, &env, ITERATIONS);
+    let mut v = match new() {
+        Ok(v) => v,
+        Err(e) => {
+            _ = views.send(Err(e));
+            return;
+        }
+    };
+    let listing = listing(&code);
+    let mut steps = 0;
+    _ = views.send(Ok((embed(&v, &listing, steps), v.done(), String::new())));
+    // until the buttons stop
+    while let Ok(p) = presses.recv() {
+        let mut note = String::new();
+        match p {
+            Press::Step(n) => {
+                for _ in 0..n {
+                    if v.done() {
+                        break;
+                    }
+                    v.step();
+                    steps += 1;
+                }
+            }
+            Press::Next => {
+                let mut found = false;
+                for _ in 0..LIMIT {
+                    if v.done() {
+                        break;
+                    }
+                    let op = listing
+                        .get(v.counter())
+                        .and_then(|(_, l)| l.split(' ').next());
+                    v.step();
+                    steps += 1;
+                    if matches!(op, Some("print" | "drawflush")) {
+                        found = true;
+                        break;
+                    }
+                }
+                if !found && !v.done() {
+                    note = format!("-# no print or drawflush in the next {LIMIT} instructions");
+                }
+            }
+            Press::Reset => {
+                v = new().unwrap();
+                steps = 0;
+            }
+        }
+        if views
+            .send(Ok((embed(&v, &listing, steps), v.done(), note)))
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+#[poise::command(
+    slash_command,
+    install_context = "User|Guild",
+    interaction_context = "Guild|BotDm|PrivateChannel"
+)]
+/// Step through MLOG from a file, an instruction at a time.
+pub async fn debug(c: Context<'_>, #[description = "logic, txt"] mlog: Attachment) -> Result<()> {
+    super::log(&c);
+    c.defer().await?;
+    let Ok(code) = String::from_utf8(mlog.download().await?) else {
+        c.say("this is not a mlog file!").await?;
+        return Ok(());
+    };
+    let (press, presses) = std::sync::mpsc::channel();
+    let (tx, mut views) = tokio::sync::mpsc::unbounded_channel();
+    let thread = tokio::task::spawn_blocking(move || session(code, presses, tx));
+    let (mut shown, done, _) = match views.recv().await {
+        Some(Ok(x)) => x,
+        Some(Err(e)) => {
+            c.send(
+                CreateReply::default()
+                    .allowed_mentions(CreateAllowedMentions::default().empty_users().empty_roles())
+                    .content(format!("```ansi\n{e}\n```")),
+            )
+            .await?;
+            return Ok(());
+        }
+        // it panicked
+        None => {
+            thread.await?;
+            return Ok(());
+        }
+    };
+    let r = poise::send_reply(
+        c,
+        CreateReply::default()
+            .embed(shown.clone())
+            .components(buttons(done)),
+    )
+    .await?;
+    let m = r.message().await?;
+    // only whoever started it may step
+    while let Some(i) = ComponentInteractionCollector::new(c.serenity_context())
+        .message_id(m.id)
+        .author_id(c.author().id)
+        .timeout(IDLE)
+        .next()
+        .await
+    {
+        let p = match &*i.data.custom_id {
+            "step" => Press::Step(1),
+            "step10" => Press::Step(10),
+            "next" => Press::Next,
+            "reset" => Press::Reset,
+            _ => continue,
+        };
+        _ = press.send(p);
+        let Some(Ok((embed, done, note))) = views.recv().await else {
+            thread.await?;
+            return Ok(());
+        };
+        shown = embed;
+        i.create_response(
+            c,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content(note)
+                    .embed(shown.clone())
+                    .components(buttons(done)),
+            ),
+        )
+        .await?;
+    }
+    drop(press);
+    r.edit(c, CreateReply::default().embed(shown).components(vec![]))
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::listing;
+
+    #[test]
+    fn every_instruction_has_a_place() {
+        let code =
+            "print 1\n# nothing\n\nloop:\n  ubind @flare # unknown to lemu\njump loop always";
+        assert_eq!(
+            listing(code),
+            [
+                (1, "print 1".to_string()),
+                (5, "ubind @flare".to_string()),
+                (6, "jump loop always".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn strings_are_kept() {
+        assert_eq!(
+            listing("print \"a: # `b`\""),
+            [(1, "print \"a: # \u{200b}`b\u{200b}`\"".to_string())]
+        );
+    }
+}
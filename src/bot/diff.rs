@@ -0,0 +1,183 @@
+use super::schematic::{self, Schem};
+use super::{AD, Context, RM, SUCCESS, strip_colors};
+use crate::emoji;
+use anyhow::Result;
+use fimg::Image;
+use mindus::Schematic;
+use mindus::block::Block;
+use mindus::data::GridPos;
+use poise::{CreateReply, serenity_prelude::*};
+use std::collections::HashMap;
+
+const CHANGED: (u8, u8, u8) = (255, 204, 0);
+
+/// A block, as far as a diff is concerned.
+#[derive(PartialEq)]
+struct Entry {
+    block: &'static Block,
+    rot: mindus::block::Rotation,
+    config: Option<mindus::data::dynamic::DynData>,
+}
+
+#[derive(Default)]
+pub struct Changes {
+    pub added: Vec<(GridPos, &'static Block)>,
+    pub removed: Vec<(GridPos, &'static Block)>,
+    pub changed: Vec<(GridPos, &'static Block)>,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        use emoji::named::*;
+        format!(
+            "{ADD} {} added — {CANCEL} {} removed — {ROTATE} {} reconfigured",
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len()
+        )
+    }
+}
+
+fn entries(s: &Schematic) -> HashMap<(usize, usize), Entry> {
+    s.block_iter()
+        .map(|(GridPos(x, y), p)| {
+            (
+                (x, y),
+                Entry {
+                    block: p.block,
+                    rot: p.rot,
+                    config: p
+                        .get_state()
+                        .and_then(|st| p.block.serialize_state(st).ok()),
+                },
+            )
+        })
+        .collect()
+}
+
+/// Compares two schematics block by block, keyed on position.
+pub fn compare(old: &Schematic, new: &Schematic) -> Changes {
+    let (old, new) = (entries(old), entries(new));
+    let mut c = Changes::default();
+    for (&(x, y), n) in &new {
+        match old.get(&(x, y)) {
+            None => c.added.push((GridPos(x, y), n.block)),
+            Some(o) if o.block != n.block => {
+                c.removed.push((GridPos(x, y), o.block));
+                c.added.push((GridPos(x, y), n.block));
+            }
+            Some(o) if o != n => c.changed.push((GridPos(x, y), n.block)),
+            Some(_) => (),
+        }
+    }
+    for (&(x, y), o) in &old {
+        if !new.contains_key(&(x, y)) {
+            c.removed.push((GridPos(x, y), o.block));
+        }
+    }
+    c
+}
+
+/// Outlines every block of `of` on top of `i`, a render of a schematic `height` tiles tall.
+fn mark(
+    i: &mut Image<Vec<u8>, 3>,
+    height: usize,
+    of: &[(GridPos, &'static Block)],
+    (r, g, b): (u8, u8, u8),
+) {
+    let scale = (i.height() as usize / height.max(1)) as u32;
+    for &(GridPos(x, y), block) in of {
+        let size = block.get_size() as usize;
+        let x0 = x.saturating_sub((size - 1) / 2);
+        let top = (y + size / 2).min(height - 1);
+        let (px, py) = (x0 as u32 * scale, (height - 1 - top) as u32 * scale);
+        let side = size as u32 * scale;
+        for inset in 0..3.min(side / 2) {
+            if px + side > i.width() || py + side > i.height() {
+                continue;
+            }
+            i.r#box(
+                (px + inset, py + inset),
+                side - inset * 2,
+                side - inset * 2,
+                [r, g, b],
+            );
+        }
+    }
+}
+
+/// Renders `new`, with added, removed and reconfigured blocks outlined.
+pub fn to_png(old: &Schematic, new: &Schematic, c: &Changes) -> Vec<u8> {
+    let render = new.render();
+    let scale = render.height() as usize / new.height.max(1);
+    // removed blocks can lie outside of `new`, so the canvas covers both
+    let (w, h) = (old.width.max(new.width), old.height.max(new.height));
+    let (rw, rh) = (render.width() as usize, render.height() as usize);
+    let (width, height) = ((w * scale).max(rw), (h * scale).max(rh));
+    let mut buf = vec![0; width * height * 3];
+    // both grow from the bottom left
+    let dy = height - rh;
+    for (y, row) in render.buffer().chunks_exact(rw * 3).enumerate() {
+        let at = (dy + y) * width * 3;
+        buf[at..at + row.len()].copy_from_slice(row);
+    }
+    let mut i = Image::build(width as u32, height as u32).buf(buf);
+    mark(&mut i, h, &c.removed, RM);
+    mark(&mut i, h, &c.changed, CHANGED);
+    mark(&mut i, h, &c.added, SUCCESS);
+    super::png(i)
+}
+
+/// Builds the diff image for two schematics, on a blocking thread.
+pub async fn image(old: Schem, new: Schem) -> Result<(Changes, Vec<u8>)> {
+    Ok(tokio::task::spawn_blocking(move || {
+        let c = compare(&old, &new);
+        let p = to_png(&old, &new, &c);
+        (c, p)
+    })
+    .await?)
+}
+
+#[poise::command(
+    slash_command,
+    install_context = "User|Guild",
+    interaction_context = "Guild|BotDm|PrivateChannel"
+)]
+/// Show what changed between two schematics.
+pub async fn diff(
+    c: Context<'_>,
+    #[description = "old schematic, base64"] old: Option<String>,
+    #[description = "old schematic, msch"] old_file: Option<Attachment>,
+    #[description = "new schematic, base64"] new: Option<String>,
+    #[description = "new schematic, msch"] new_file: Option<Attachment>,
+) -> Result<()> {
+    super::log(&c);
+    c.defer().await?;
+    let (Some(old), Some(new)) = (
+        schematic::from_either(old, old_file).await?,
+        schematic::from_either(new, new_file).await?,
+    ) else {
+        poise::say_reply(c, "need two schematics (base64 or msch)").await?;
+        return Ok(());
+    };
+    let name = emoji::mindustry::to_discord(&strip_colors(new.tags.get("name").unwrap()));
+    let (changes, png) = image(old, new).await?;
+    poise::send_reply(
+        c,
+        CreateReply::default()
+            .attachment(CreateAttachment::bytes(png, "diff.png"))
+            .embed(
+                CreateEmbed::new()
+                    .title(name)
+                    .description(changes.summary())
+                    .attachment("diff.png")
+                    .color(AD),
+            ),
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,148 @@
+// index of what each repo already holds, so submissions dont need a scan through search::schems.
+// it fills in as schematics are added; for what a repo held before, a chief runs /reindex once.
+use super::repos::Repo;
+use super::schematic::Schem;
+use kv::*;
+use mindus::Schematic;
+use poise::serenity_prelude::*;
+use std::hash::BuildHasher;
+
+/// (exact, content), content ignoring name, description and labels
+pub fn hashes(s: &Schematic) -> (u64, u64) {
+    let h = rustc_hash::FxBuildHasher::default();
+    let mut bare = s.clone();
+    bare.tags.clear();
+    (
+        h.hash_one(&Schem { schem: s.clone() }),
+        h.hash_one(&Schem { schem: bare }),
+    )
+}
+
+fn bucket(r: &Repo, kind: &str) -> Bucket<'static, Integer, Vec<u8>> {
+    super::db::store()
+        .bucket(Some(&format!("{}-{kind}", r.name)))
+        .unwrap()
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Post {
+    pub channel: u64,
+    pub message: u64,
+}
+
+impl Post {
+    fn bytes(self) -> [u8; 16] {
+        let mut b = [0; 16];
+        b[..8].copy_from_slice(&self.channel.to_le_bytes());
+        b[8..].copy_from_slice(&self.message.to_le_bytes());
+        b
+    }
+    /// Every post stored under one hash, oldest first.
+    fn all(b: Vec<u8>) -> Vec<Self> {
+        b.chunks_exact(16)
+            .map(|x| {
+                let (c, m) = x.split_at(8);
+                Self {
+                    channel: u64::from_le_bytes(c.try_into().unwrap()),
+                    message: u64::from_le_bytes(m.try_into().unwrap()),
+                }
+            })
+            .collect()
+    }
+}
+
+pub enum Dupe {
+    /// the very same schematic
+    Exact(Post),
+    /// same blocks, different name or tags
+    Near(Post),
+}
+
+/// The oldest post `s` duplicates, other than `message` itself.
+pub fn find(r: &Repo, s: &Schematic, message: u64) -> Option<Dupe> {
+    let (exact, content) = hashes(s);
+    let get = |kind, h: u64| {
+        bucket(r, kind)
+            .get(&h.into())
+            .ok()
+            .flatten()
+            .map(Post::all)
+            .into_iter()
+            .flatten()
+            .find(|p| p.message != message)
+    };
+    get("exact", exact)
+        .map(Dupe::Exact)
+        .or_else(|| get("content", content).map(Dupe::Near))
+}
+
+pub fn insert(r: &Repo, s: &Schematic, p: Post) {
+    let (exact, content) = hashes(s);
+    for (kind, h) in [("exact", exact), ("content", content)] {
+        let b = bucket(r, kind);
+        // appended, so the first post keeps being the one duplicates point at
+        let mut v = b.get(&h.into()).ok().flatten().unwrap_or_default();
+        if !Post::all(v.clone()).iter().any(|x| x.message == p.message) {
+            v.extend(p.bytes());
+            b.set(&h.into(), &v).unwrap();
+            b.flush().unwrap();
+        }
+    }
+}
+
+pub fn remove(r: &Repo, s: &Schematic, message: u64) {
+    let (exact, content) = hashes(s);
+    for (kind, h) in [("exact", exact), ("content", content)] {
+        let b = bucket(r, kind);
+        let Ok(Some(x)) = b.get(&h.into()) else {
+            continue;
+        };
+        let rest = Post::all(x)
+            .into_iter()
+            .filter(|p| p.message != message)
+            .flat_map(Post::bytes)
+            .collect::<Vec<_>>();
+        if rest.is_empty() {
+            b.remove(&h.into()).unwrap();
+        } else {
+            b.set(&h.into(), &rest).unwrap();
+        }
+        b.flush().unwrap();
+    }
+}
+
+#[poise::command(slash_command)]
+/// Rebuild the duplicate index of this channel's repo.
+///
+/// Needed once for schematics added before the index existed; they are not checked against until then.
+pub async fn reindex(c: super::Context<'_>) -> anyhow::Result<()> {
+    use super::{OWNER, repos};
+    let repo = repos::chief!(c);
+    c.defer().await?;
+    // whatever was deleted while the bot was away goes too
+    for kind in ["exact", "content"] {
+        let b = bucket(repo, kind);
+        b.clear()?;
+        b.flush()?;
+    }
+    let mut n = 0;
+    for (&channel, ch) in repos::SPECIAL.entries().filter(|(_, ch)| ch.repo == repo) {
+        let Ok(dir) = std::fs::read_dir(repo.repopath().join(ch.d)) else {
+            continue;
+        };
+        for f in dir.flatten().map(|f| f.path()) {
+            insert(
+                repo,
+                &super::search::load(&f),
+                Post {
+                    channel,
+                    message: super::search::flake(&f.file_name().unwrap().to_string_lossy()),
+                },
+            );
+            n += 1;
+        }
+    }
+    c.say(format!("{} indexed {n} schematics", crate::emoji::named::OK))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,88 @@
+// a tiny 3×5 pixel font, for numbering and labelling renders.
+
+/// 3×5 digits, a row of three bits each, msb left.
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+/// 3×5 letters, like [`DIGITS`].
+const LETTERS: [[u8; 5]; 26] = [
+    [0b010, 0b101, 0b111, 0b101, 0b101],
+    [0b110, 0b101, 0b110, 0b101, 0b110],
+    [0b011, 0b100, 0b100, 0b100, 0b011],
+    [0b110, 0b101, 0b101, 0b101, 0b110],
+    [0b111, 0b100, 0b110, 0b100, 0b111],
+    [0b111, 0b100, 0b110, 0b100, 0b100],
+    [0b011, 0b100, 0b101, 0b101, 0b011],
+    [0b101, 0b101, 0b111, 0b101, 0b101],
+    [0b111, 0b010, 0b010, 0b010, 0b111],
+    [0b001, 0b001, 0b001, 0b101, 0b010],
+    [0b101, 0b101, 0b110, 0b101, 0b101],
+    [0b100, 0b100, 0b100, 0b100, 0b111],
+    [0b101, 0b111, 0b111, 0b101, 0b101],
+    [0b110, 0b101, 0b101, 0b101, 0b101],
+    [0b010, 0b101, 0b101, 0b101, 0b010],
+    [0b110, 0b101, 0b110, 0b100, 0b100],
+    [0b010, 0b101, 0b101, 0b110, 0b011],
+    [0b110, 0b101, 0b110, 0b101, 0b101],
+    [0b011, 0b100, 0b010, 0b001, 0b110],
+    [0b111, 0b010, 0b010, 0b010, 0b010],
+    [0b101, 0b101, 0b101, 0b101, 0b111],
+    [0b101, 0b101, 0b101, 0b101, 0b010],
+    [0b101, 0b101, 0b111, 0b111, 0b101],
+    [0b101, 0b101, 0b010, 0b101, 0b101],
+    [0b101, 0b101, 0b010, 0b010, 0b010],
+    [0b111, 0b001, 0b010, 0b100, 0b111],
+];
+
+fn glyph(c: u8) -> [u8; 5] {
+    match c {
+        b'0'..=b'9' => DIGITS[(c - b'0') as usize],
+        b'a'..=b'z' => LETTERS[(c - b'a') as usize],
+        b'A'..=b'Z' => LETTERS[(c - b'A') as usize],
+        b'-' => [0, 0, 0b111, 0, 0],
+        _ => [0; 5],
+    }
+}
+
+/// Draws `s` in white, `scale` times the size of the 3×5 font, from `(x, y)`.
+/// Without a `bg`, only the glyphs are drawn.
+pub fn text(
+    s: &str,
+    (x, y): (i64, i64),
+    scale: i64,
+    bg: Option<[u8; 3]>,
+    mut put: impl FnMut(i64, i64, [u8; 3]),
+) {
+    for (k, ch) in s.bytes().enumerate() {
+        for (row, bits) in glyph(ch).iter().enumerate() {
+            for col in 0..3 {
+                let on = bits >> (2 - col) & 1 == 1;
+                let Some(c) = (if on { Some([255, 255, 255]) } else { bg }) else {
+                    continue;
+                };
+                for (px, py) in (0..scale).flat_map(|a| (0..scale).map(move |b| (a, b))) {
+                    put(
+                        x + (k as i64 * 4 + col) * scale + px,
+                        y + row as i64 * scale + py,
+                        c,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Draws `n` in white on `bg`, at three times the size of [`DIGITS`], from `(x, y)`.
+pub fn number(n: usize, at: (i64, i64), bg: [u8; 3], put: impl FnMut(i64, i64, [u8; 3])) {
+    text(&n.to_string(), at, 3, Some(bg), put)
+}
@@ -0,0 +1,226 @@
+use super::schematic::{self, Schem};
+use super::{Context, RM, repos, strip_colors};
+use crate::emoji;
+use anyhow::Result;
+use mindus::Schematic;
+use mindus::block::Block;
+use mindus::block::logic::ProcessorState;
+use mindus::data::GridPos;
+use mindus::data::dynamic::DynData;
+use poise::{CreateReply, serenity_prelude::*};
+use std::fmt::Write;
+
+pub struct Finding {
+    pub at: GridPos,
+    pub block: &'static Block,
+    pub what: &'static str,
+}
+
+/// Tile -> the position of the block covering it.
+fn occupancy(s: &Schematic) -> Vec<Option<(GridPos, &'static Block)>> {
+    let mut o = vec![None; s.width * s.height];
+    for (pos @ GridPos(x, y), p) in s.block_iter() {
+        let size = p.block.get_size() as usize;
+        for tx in x.saturating_sub((size - 1) / 2)..(x + size / 2 + 1).min(s.width) {
+            for ty in y.saturating_sub((size - 1) / 2)..(y + size / 2 + 1).min(s.height) {
+                o[ty * s.width + tx] = Some((pos, p.block));
+            }
+        }
+    }
+    o
+}
+
+pub fn check(s: &Schematic) -> Vec<Finding> {
+    let o = occupancy(s);
+    let at = |x: i32, y: i32| {
+        (x >= 0 && y >= 0 && (x as usize) < s.width && (y as usize) < s.height)
+            .then(|| o[y as usize * s.width + x as usize])
+            .flatten()
+    };
+    let config = |p: &mindus::data::schematic::Placement| {
+        p.get_state()
+            .and_then(|st| p.block.serialize_state(st).ok())
+            .unwrap_or(DynData::Empty)
+    };
+    // the end of a bridge chain has no link of its own, but something links to it
+    let linked = s
+        .block_iter()
+        .filter_map(|(GridPos(x, y), p)| match config(p) {
+            DynData::Point2(dx, dy) if (dx, dy) != (0, 0) => Some((x as i32 + dx, y as i32 + dy)),
+            _ => None,
+        })
+        .collect::<std::collections::HashSet<_>>();
+    let mut f = vec![];
+    for (pos @ GridPos(x, y), p) in s.block_iter() {
+        let config = config(p);
+        let mut flag = |what| {
+            f.push(Finding {
+                at: pos,
+                block: p.block,
+                what,
+            })
+        };
+        match p.block.name() {
+            "power-node" | "power-node-large" | "surge-tower" => match config {
+                DynData::Point2Array(ref v) if !v.is_empty() => (),
+                _ => flag("power node without links"),
+            },
+            "micro-processor" | "logic-processor" | "hyper-processor" | "world-processor" => {
+                match p.get_state().and_then(|s| s.downcast_ref::<ProcessorState>()) {
+                    Some(ProcessorState { code, .. }) if code.trim().is_empty() => {
+                        flag("processor without code")
+                    }
+                    Some(ProcessorState { links, .. }) if links.is_empty() => {
+                        flag("processor without links")
+                    }
+                    Some(_) => (),
+                    None => flag("processor without code"),
+                }
+            }
+            "sorter" | "inverted-sorter" => match config {
+                DynData::Content(..) => (),
+                _ => flag("sorter without an item"),
+            },
+            "bridge-conveyor" | "phase-conveyor" | "bridge-conduit" | "phase-conduit"
+            | "mass-driver" => match config {
+                DynData::Point2(dx, dy) if (dx, dy) != (0, 0) => {
+                    if at(x as i32 + dx, y as i32 + dy).is_none_or(|(_, b)| b != p.block) {
+                        flag("bridge pointing nowhere");
+                    }
+                }
+                _ if !linked.contains(&(x as i32, y as i32)) => flag("bridge without links"),
+                _ => (),
+            },
+            "unloader" => {
+                let touches = [(0, 1), (1, 0), (0, -1), (-1, 0)].iter().any(|(dx, dy)| {
+                    at(x as i32 + dx, y as i32 + dy).is_some_and(|(_, b)| {
+                        matches!(
+                            b.name(),
+                            "container" | "vault" | "reinforced-container" | "reinforced-vault"
+                        ) || b.name().starts_with("core-")
+                    })
+                });
+                if !touches {
+                    flag("unloader without a container, vault or core to unload from");
+                }
+            }
+            _ => (),
+        }
+    }
+    f
+}
+
+/// Circles and numbers every finding on a render of `s`.
+pub fn to_png(s: &Schematic, f: &[Finding]) -> Vec<u8> {
+    let i = s.render();
+    let (w, h) = (i.width() as i64, i.height() as i64);
+    let scale = w / s.width.max(1) as i64;
+    let mut buf = i.take_buffer();
+    let (r, g, b) = RM;
+    let mut put = |x: i64, y: i64, c: [u8; 3]| {
+        if (0..w).contains(&x) && (0..h).contains(&y) {
+            let n = (y * w + x) as usize * 3;
+            buf[n..n + 3].copy_from_slice(&c);
+        }
+    };
+    for (n, Finding { at: GridPos(x, y), block, .. }) in (1..).zip(f) {
+        let size = block.get_size() as i64;
+        let off = (size - 1) / 2;
+        // center, in pixels
+        let cx = (*x as i64 - off) * scale + size * scale / 2;
+        let cy = (s.height as i64 - 1 - (*y as i64 + size / 2)) * scale + size * scale / 2;
+        let radius = size * scale * 3 / 4;
+        for dy in -radius - 2..=radius + 2 {
+            for dx in -radius - 2..=radius + 2 {
+                let d = ((dx * dx + dy * dy) as f32).sqrt();
+                if (d - radius as f32).abs() <= 2.0 {
+                    put(cx + dx, cy + dy, [r, g, b]);
+                }
+            }
+        }
+        // number, top left of the circle
+        super::font::number(n, (cx - radius, cy - radius - 6 * 3), [r, g, b], &mut put);
+    }
+    super::png(fimg::Image::build(w as u32, h as u32).buf(buf))
+}
+
+pub fn list(f: &[Finding]) -> String {
+    let mut s = String::new();
+    for (n, Finding { at: GridPos(x, y), block, what }) in (1..).zip(f) {
+        let line = format!("{n}. `{}` at ({x}, {y}): {what}\n", block.name());
+        // leave space for the note
+        if s.len() + line.len() > 3900 {
+            write!(s, "…and {} more", f.len() - n + 1).unwrap();
+            break;
+        }
+        s.push_str(&line);
+    }
+    s
+}
+
+/// The annotated render and the findings, or [`None`] if there are none.
+pub async fn report(s: &Schematic) -> Result<Option<(CreateAttachment, CreateEmbed)>> {
+    let s = s.clone();
+    let Some((png, l)) = tokio::task::spawn_blocking(move || {
+        let f = check(&s);
+        (!f.is_empty()).then(|| (to_png(&s, &f), list(&f)))
+    })
+    .await?
+    else {
+        return Ok(None);
+    };
+    Ok(Some((
+        CreateAttachment::bytes(png, "lint.png"),
+        CreateEmbed::new()
+            .title(format!("{} findings", emoji::named::WARNING))
+            .description(l)
+            .attachment("lint.png")
+            .color(RM),
+    )))
+}
+
+#[poise::command(
+    slash_command,
+    install_context = "User|Guild",
+    interaction_context = "Guild|BotDm|PrivateChannel"
+)]
+/// Check a schematic for common mistakes.
+pub async fn lint(
+    c: Context<'_>,
+    #[description = "schematic, base64"] s: Option<String>,
+    #[description = "schematic, msch"] file: Option<Attachment>,
+) -> Result<()> {
+    use emoji::named::*;
+    super::log(&c);
+    c.defer().await?;
+    let Some(Schem { schem: s }) = schematic::from_either(s, file).await? else {
+        c.reply("no schem!").await?;
+        return Ok(());
+    };
+    let name = emoji::mindustry::to_discord(&strip_colors(s.tags.get("name").unwrap()));
+    poise::send_reply(
+        c,
+        match report(&s).await? {
+            Some((a, e)) => CreateReply::default().attachment(a).embed(e.title(name)),
+            None => CreateReply::default().content(format!("{OK} nothing to report in {name}")),
+        },
+    )
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command)]
+/// Toggle linting of new submissions in this channel.
+pub async fn lint_channel(c: Context<'_>) -> Result<()> {
+    use super::OWNER;
+    use emoji::named::*;
+    let _ = repos::chief!(c);
+    let on = super::db::toggle_lint(c.channel_id().get());
+    c.say(format!(
+        "{OK} linting submissions in <#{}> is now {}",
+        c.channel_id(),
+        if on { "on" } else { "off" }
+    ))
+    .await?;
+    Ok(())
+}
@@ -1,16 +1,29 @@
+use super::pack::Display;
+use super::world::{self, Env, World};
 use super::{Context, Result};
 use lemu::Executor;
 use poise::{CodeBlock, KeyValueArgs, serenity_prelude::*};
+use std::fmt::Write;
 
 #[poise::command(slash_command, rename = "eval_file", install_context = "Guild|User")]
 /// Execute MLOG from a file.
 ///
 /// Your file can run up to 52789849 instructions, and up to 50 iterations.
-/// You get one large display to use.
+/// You get one large display to use, unless you ask for others.
+/// With `animate`, the displays are captured after every iteration, as a gif.
+/// `dump` shows the variables and memory afterwards.
 pub async fn run_file(
     ctx: Context<'_>,
     #[description = "logic, txt"] mlog: Attachment,
     #[description = "number of iterations (0–50)"] iterations: Option<u8>,
+    #[description = "value of @tick"] tick: Option<f64>,
+    #[description = "value of @time, in ms"] time: Option<f64>,
+    #[description = "seed, making op rand deterministic"] seed: Option<u64>,
+    #[description = "display sizes, eg large,small,small"] displays: Option<String>,
+    #[description = "capture the displays after every iteration, as a gif"] animate: Option<bool>,
+    #[description = "show afterwards: vars, memory or vars,memory"] dump: Option<String>,
+    #[description = "world json: memory cells, switches and sensor values (see /help eval)"]
+    world: Option<Attachment>,
 ) -> Result<()> {
     super::log(&ctx);
     ctx.defer().await?;
@@ -19,7 +32,44 @@ pub async fn run_file(
         ctx.say("this is not a mlog file!").await?;
         return Ok(());
     };
-    match exec(code, iterations.map_or(1, |x| x.clamp(0, 50)) as _).await {
+    let world = match world {
+        Some(w) => match String::from_utf8(w.download().await?)
+            .map_err(|_| "the world is not json".to_string())
+            .and_then(|x| World::parse(&x))
+        {
+            Ok(x) => x,
+            Err(e) => {
+                ctx.say(e).await?;
+                return Ok(());
+            }
+        },
+        None => World::default(),
+    };
+    let displays = match displays.as_deref().map(world::displays).transpose() {
+        Ok(x) => x.unwrap_or_default(),
+        Err(e) => {
+            ctx.say(e).await?;
+            return Ok(());
+        }
+    };
+    let dump = match dump.as_deref().map(world::dump).transpose() {
+        Ok(x) => x.unwrap_or_default(),
+        Err(e) => {
+            ctx.say(e).await?;
+            return Ok(());
+        }
+    };
+    let env = Env {
+        tick,
+        time,
+        seed,
+        displays,
+        animate: animate.unwrap_or(false),
+        dump,
+        world,
+    };
+    let iters = iterations.map_or(if env.animate { 50 } else { 1 }, |x| x.clamp(0, 50));
+    match exec(code, iters as _, env).await {
         Err(Err::Other(x)) => return Err(x),
         Err(Err::Lemu(x)) => {
             ctx.send(
@@ -39,14 +89,26 @@ pub async fn run_file(
 #[poise::command(prefix_command, track_edits, rename = "eval")]
 pub async fn run(
     ctx: Context<'_>,
-    #[description = "number of iterations"] kv: KeyValueArgs,
+    #[description = "number of iterations, tick, time, seed, displays, animate and dump"]
+    kv: KeyValueArgs,
     #[description = "Script"] block: CodeBlock,
+    #[description = "world json"] world: Option<CodeBlock>,
 ) -> Result<()> {
     super::log(&ctx);
+    let env = match Env::new(&kv, world.as_ref().map(|x| &*x.code)) {
+        Ok(x) => x,
+        Err(e) => {
+            ctx.say(e).await?;
+            return Ok(());
+        }
+    };
     match exec(
         block.code,
         kv.get("iters")
-            .map_or(1, |v| v.parse::<usize>().unwrap_or(1).clamp(1, 50)),
+            .map_or(if env.animate { 50 } else { 1 }, |v| {
+                v.parse::<usize>().unwrap_or(1).clamp(1, 50)
+            }),
+        env,
     )
     .await
     {
@@ -64,7 +126,7 @@ pub async fn run(
     Ok(())
 }
 
-enum Err {
+pub(super) enum Err {
     Lemu(String),
     Other(anyhow::Error),
 }
@@ -74,22 +136,115 @@ impl<T: Into<anyhow::Error>> From<T> for Err {
     }
 }
 
-async fn exec(code: String, iters: usize) -> Result<poise::CreateReply, Err> {
-    let lemu::Output {
-        output: Some(output),
-        displays,
-        ..
-    } = (match tokio::task::spawn_blocking(move || {
-        Executor::with_output(vec![])
-            .large_display()
-            .limit_iterations(iters)
-            .limit_instructions(52789849)
-            .program(&code)
-            .map(|mut v| {
+/// Parses `code` without running it, with `env`'s links, producing the diagnostic on failure.
+pub(super) fn check(code: &str, env: &Env) -> Result<(), String> {
+    executor(code, env, 1).map(drop)
+}
+
+/// Sets up `code` (already rewritten by [`Env::apply`]) with `env`'s displays and memory.
+pub(super) fn executor<'s>(
+    code: &'s str,
+    env: &Env,
+    iters: usize,
+) -> Result<Executor<'s, Vec<u8>>, String> {
+    linked(
+        code,
+        if env.displays.is_empty() {
+            &[Display::Large]
+        } else {
+            &env.displays
+        },
+        env.memory(code),
+        iters,
+    )
+}
+
+/// Sets up `code` with exactly these displays, cells and banks linked.
+pub(super) fn linked<'s>(
+    code: &'s str,
+    displays: &[Display],
+    (cells, banks): (usize, usize),
+    iters: usize,
+) -> Result<Executor<'s, Vec<u8>>, String> {
+    let mut e = Executor::with_output(vec![]);
+    for d in displays {
+        e = match d {
+            Display::Small => e.display(),
+            Display::Large => e.large_display(),
+        };
+    }
+    for _ in 0..cells {
+        e = e.cell();
+    }
+    for _ in 0..banks {
+        e = e.bank();
+    }
+    e.limit_iterations(iters)
+        .limit_instructions(52789849)
+        .program(code)
+        .map_err(|e| format!("{}", e.diagnose(code)).replace('`', "\u{200b}`"))
+}
+
+/// (name, value) of the program's variables.
+pub(super) fn variables(v: &Executor<'_, Vec<u8>>) -> Vec<(String, String)> {
+    v.variables()
+        // the rewrite's own variables are not the program's
+        .filter(|(k, _)| !k.starts_with("__"))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+/// Variables, aligned, one a line.
+pub(super) fn table(vars: &[(String, String)]) -> String {
+    if vars.is_empty() {
+        return "no variables\n".to_string();
+    }
+    let w = vars.iter().map(|(k, _)| k.len()).max().unwrap_or(0);
+    vars.iter()
+        .map(|(k, v)| format!("{k:w$} {v}\n"))
+        .collect::<String>()
+        .replace('`', "\u{200b}`")
+}
+
+pub(super) async fn exec(code: String, iters: usize, env: Env) -> Result<poise::CreateReply, Err> {
+    // diagnostics should point at what was written, not the rewrite
+    check(&code, &env).map_err(Err::Lemu)?;
+    let code = env.apply(&code).map_err(Err::Lemu)?;
+    let described = env.describe();
+    let dump = env.dump;
+    let (
+        lemu::Output {
+            output: Some(output),
+            displays,
+            cells,
+            banks,
+            ..
+        },
+        frames,
+        vars,
+    ) = (match tokio::task::spawn_blocking(move || {
+        executor(&code, &env, iters).map(|mut v| {
+            let mut frames: Vec<(Frame, u16)> = vec![];
+            if env.animate {
+                while !v.done() {
+                    let i = v.iterations();
+                    v.step();
+                    if v.iterations() == i {
+                        continue;
+                    }
+                    let f = tile(v.displays().iter().map(|(d, _)| d));
+                    match frames.last_mut() {
+                        // a still display need not be stored twice
+                        Some((last, n)) if *last == f => *n += 1,
+                        _ => frames.push((f, 1)),
+                    }
+                }
+            } else {
                 v.run();
-                v.output()
-            })
-            .map_err(|e| format!("{}", e.diagnose(&code)).replace('`', "\u{200b}`"))
+            }
+            let vars = dump.vars.then(|| variables(&v)).unwrap_or_default();
+            (v.output(), frames, vars)
+        })
     })
     .await?
     {
@@ -101,11 +256,20 @@ async fn exec(code: String, iters: usize) -> Result<poise::CreateReply, Err> {
     else {
         unreachable!()
     };
-    let displays: Box<[_; 1]> = displays.try_into().unwrap();
-    let [(display, _)] = *displays;
-    let display = if display.buffer().iter().any(|&n| n != 0) {
-        Some(
-            tokio::task::spawn_blocking(move || {
+    let animation = if frames.len() > 1 {
+        Some(tokio::task::spawn_blocking(move || gif(frames)).await?)
+    } else {
+        None
+    };
+    let animated = animation.is_some();
+    let mut displays = tokio::task::spawn_blocking(move || {
+        displays
+            .into_iter()
+            // the gif shows them already
+            .filter(|_| !animated)
+            .enumerate()
+            .filter(|(_, (d, _))| d.buffer().iter().any(|&n| n != 0))
+            .map(|(n, (display, _))| {
                 let p = oxipng::RawImage::new(
                     display.width(),
                     display.height(),
@@ -114,16 +278,20 @@ async fn exec(code: String, iters: usize) -> Result<poise::CreateReply, Err> {
                     display.take_buffer(),
                 )
                 .unwrap();
-                p.create_optimized_png(&oxipng::Options::default()).unwrap()
+                (
+                    format!("display{}.png", n + 1),
+                    p.create_optimized_png(&oxipng::Options::default()).unwrap(),
+                )
             })
-            .await?,
-        )
-    } else {
-        None
-    };
+            .collect::<Vec<_>>()
+    })
+    .await?;
+    if let Some(animation) = animation {
+        displays.push(("display.gif".to_string(), animation));
+    }
 
     let mut c = poise::CreateReply::default();
-    if output.is_empty() && display.is_none() {
+    if output.is_empty() && displays.is_empty() && !dump.vars && !dump.memory {
         c = c.content("no output");
     }
     if !output.is_empty() {
@@ -132,10 +300,102 @@ async fn exec(code: String, iters: usize) -> Result<poise::CreateReply, Err> {
             String::from_utf8_lossy(&output).replace('`', "\u{200b}`")
         ));
     }
-    if let Some(display) = display {
-        c = c
-            .attachment(CreateAttachment::bytes(display, "display1.png"))
-            .embed(CreateEmbed::default().attachment("display1.png"));
+    let many = displays.len() > 1;
+    for (i, (name, png)) in displays.into_iter().enumerate() {
+        let mut e = CreateEmbed::default().attachment(&name);
+        if many {
+            e = e.title(name.trim_end_matches(".png"));
+        }
+        if i == 0
+            && let Some(d) = &described
+        {
+            e = e.footer(CreateEmbedFooter::new(d));
+        }
+        c = c.attachment(CreateAttachment::bytes(png, name)).embed(e);
+    }
+    if dump.vars {
+        let table = table(&vars);
+        c = if table.len() < 4000 && c.embeds.len() < 10 {
+            c.embed(
+                CreateEmbed::default()
+                    .title("variables")
+                    .description(format!("```\n{table}```")),
+            )
+        } else {
+            c.attachment(CreateAttachment::bytes(table, "variables.txt"))
+        };
+    }
+    if dump.memory {
+        // only what was used, as untouched memory is all zeroes
+        let csv = (1..)
+            .zip(&cells)
+            .map(|(n, x)| (format!("cell{n}"), x.to_vec()))
+            .chain(
+                (1..)
+                    .zip(&banks)
+                    .map(|(n, x)| (format!("bank{n}"), x.to_vec())),
+            )
+            .filter(|(_, x)| x.iter().any(|&v| v != 0.0))
+            .map(|(mut row, x)| {
+                for v in x {
+                    write!(row, ",{v}").unwrap();
+                }
+                row + "\n"
+            })
+            .collect::<String>();
+        if !csv.is_empty() {
+            c = c.attachment(CreateAttachment::bytes(csv, "memory.csv"));
+        } else if c.embeds.len() < 10 {
+            c = c.embed(
+                CreateEmbed::default()
+                    .title("memory")
+                    .description("untouched"),
+            );
+        }
+    }
+    if c.embeds.is_empty()
+        && let Some(d) = described
+    {
+        c = c.embed(CreateEmbed::default().description(format!("-# {d}")));
     }
     Ok(c)
 }
+
+/// What the displays show, side by side: (width, height, rgba).
+type Frame = (u32, u32, Vec<u8>);
+
+fn tile<'a>(displays: impl Iterator<Item = &'a fimg::Image<Vec<u8>, 4>> + Clone) -> Frame {
+    const PAD: u32 = 4;
+    let w = displays.clone().map(|d| d.width() + PAD).sum::<u32>() - PAD;
+    let h = displays.clone().map(|d| d.height()).max().unwrap_or(0);
+    let mut buf = vec![0; (w * h * 4) as usize];
+    let mut x0 = 0;
+    for d in displays {
+        let dw = d.width() as usize;
+        for (y, row) in d.buffer().chunks_exact(dw * 4).enumerate() {
+            let at = (y * w as usize + x0) * 4;
+            buf[at..at + row.len()].copy_from_slice(row);
+        }
+        x0 += dw + PAD as usize;
+    }
+    (w, h, buf)
+}
+
+/// Frames, with how many iterations each was shown for.
+fn gif(frames: Vec<(Frame, u16)>) -> Vec<u8> {
+    use image::codecs::gif::{GifEncoder, Repeat};
+    let mut out = vec![];
+    let mut e = GifEncoder::new_with_speed(&mut out, 10);
+    e.set_repeat(Repeat::Infinite).unwrap();
+    e.encode_frames(frames.into_iter().map(|((w, h, buf), n)| {
+        image::Frame::from_parts(
+            image::RgbaImage::from_raw(w, h, buf).unwrap(),
+            0,
+            0,
+            image::Delay::from_numer_denom_ms(100 * n as u32, 1),
+        )
+    }))
+    .unwrap();
+    drop(e);
+    out
+}
@@ -2,14 +2,14 @@ use anyhow::Result;
 use mindus::{data::map::ReadError, *};
 use poise::{CreateReply, serenity_prelude::*};
 use std::{
+    fmt::Write,
     ops::ControlFlow,
     time::{Duration, Instant},
 };
 use tokio::task::JoinError;
-const BENDN: ChannelId = ChannelId::new(1149866218057117747);
 use super::{SUCCESS, strip_colors};
 
-fn string((x, f): (ReadError, &str)) -> String {
+pub(super) fn string((x, f): (ReadError, &str)) -> String {
     match x {
         ReadError::Decompress(_) | ReadError::Header(_) => {
             format!("not a map.")
@@ -35,53 +35,76 @@ fn string((x, f): (ReadError, &str)) -> String {
     }
 }
 
+/// Deserializes off the runtime, as big maps take a while.
+async fn read(s: Box<[u8]>) -> Result<(Result<(Map, Box<[u8]>), ReadError>, Duration)> {
+    Ok(tokio::task::spawn_blocking(move || {
+        let then = Instant::now();
+        let m = Map::deserialize(&mut mindus::data::DataRead::new(&s));
+        (m.map(|x| (x, s)), then.elapsed())
+    })
+    .await?)
+}
+
 pub async fn download(
     a: &Attachment,
 ) -> Result<(Result<(Map, Box<[u8]>), (ReadError, &str)>, Duration)> {
     let s = a.download().await?.into_boxed_slice();
-    let then = Instant::now();
-
     // could ignore, but i think if you have a msav, you dont want to ignore failures.
-    Ok((
-        Map::deserialize(&mut mindus::data::DataRead::new(&s))
-            .map_err(|x| (x, &*a.filename))
-            .map(|x| (x, s)),
-        then.elapsed(),
-    ))
+    let (m, took) = read(s).await?;
+    Ok((m.map_err(|x| (x, &*a.filename)), took))
 }
 
+/// The first map in the message, and how many other maps its archive held.
 pub async fn scour(
     m: &Message,
-) -> Result<Option<(Result<(Map, Box<[u8]>), (ReadError, &str)>, Duration)>> {
+) -> Result<Option<(Result<(Map, Box<[u8]>), (ReadError, &str)>, Duration, usize)>> {
     for a in &m.attachments {
         if a.filename.ends_with("msav") {
-            return Ok(Some(download(a).await?));
+            let (m, took) = download(a).await?;
+            return Ok(Some((m, took, 0)));
+        }
+        if super::archive::is(a) {
+            let mut maps = super::archive::download(a)
+                .await?
+                .into_iter()
+                .filter(|(n, _)| n.ends_with("msav"));
+            let Some((_, s)) = maps.next() else { continue };
+            let (m, took) = read(s.into_boxed_slice()).await?;
+            return Ok(Some((m.map_err(|x| (x, &*a.filename)), took, maps.count())));
         }
     }
     Ok(None)
 }
 
+/// Reports a failed render of `b`.
+fn crash(c: super::Context<'_>, b: &[u8], e: JoinError) -> u64 {
+    use super::crashes;
+    let (error, backtrace) = crashes::panicked(e);
+    crashes::file(
+        Some((crashes::Kind::Map, b)),
+        &error,
+        backtrace,
+        crashes::guild(c.serenity_context(), c.guild_id(), c.author()),
+        &c.command().qualified_name,
+    )
+}
+
 pub async fn reply(
     c: super::Context<'_>,
     a: &Attachment,
+    o: Options,
 ) -> Result<ControlFlow<CreateReply, String>> {
     let ((m, b), deser_took) = match download(a).await? {
         (Err(e), _) => return Ok(ControlFlow::Continue(string(e))),
         (Ok(m), deser_took) => (m, deser_took),
     };
-    let (a, e) = match embed(m, deser_took).await {
+    let (a, e) = match embed(m, deser_took, o).await {
         Ok(x) => x,
         Err(e) => {
-            BENDN
-                .send_files(
-                    &c,
-                    [CreateAttachment::bytes(b, "map.msav")],
-                    CreateMessage::new().content(format!("<@696196765564534825>　failure: {e}")),
-                )
-                .await?;
-            return Ok(ControlFlow::Break(CreateReply::default().content(
-                "there was a problem. i have notified bendn about this issue.",
-            )));
+            let id = crash(c, &b, e);
+            return Ok(ControlFlow::Break(CreateReply::default().content(format!(
+                "there was a problem. it has been logged as crash #{id}."
+            ))));
         }
     };
     Ok(ControlFlow::Break(
@@ -95,9 +118,277 @@ struct Timings {
     compression_took: Duration,
     total: Duration,
 }
-fn render(m: Map, deser_took: Duration) -> (Timings, Vec<u8>) {
-    let render_took = Instant::now();
+#[derive(poise::ChoiceParameter, Copy, Clone, Debug, Default, PartialEq)]
+pub enum Layer {
+    #[default]
+    #[name = "everything"]
+    All,
+    #[name = "floor only"]
+    Floor,
+    /// dims everything that isnt an ore
+    #[name = "ores highlighted"]
+    Ores,
+    #[name = "buildings only"]
+    Buildings,
+    /// buildings, tinted by team
+    #[name = "team-colored buildings"]
+    Teams,
+    /// terrain colored by how dense each resource is
+    #[name = "resource heatmap"]
+    Resources,
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Options {
+    pub layer: Layer,
+    /// x, y, w, h in tiles, from the bottom left
+    pub crop: Option<(usize, usize, usize, usize)>,
+}
+
+impl Options {
+    pub fn parse(layer: Option<Layer>, crop: Option<&str>) -> Result<Self, String> {
+        let crop = crop
+            .map(|c| match numbers(c).as_deref() {
+                Some(&[x, y, w, h]) if w != 0 && h != 0 => Ok((x, y, w, h)),
+                _ => Err(format!("`{c}` is not a crop: `x, y, w, h`")),
+            })
+            .transpose()?;
+        Ok(Self {
+            layer: layer.unwrap_or_default(),
+            crop,
+        })
+    }
+}
+
+/// Tile coordinates and sizes, `1, 2` or `1 2`.
+pub(super) fn numbers(s: &str) -> Option<Vec<usize>> {
+    s.split(|x: char| x == ',' || x.is_whitespace())
+        .filter(|x| !x.is_empty())
+        .map(|x| x.parse().ok())
+        .collect()
+}
+
+fn team_color(team: &str) -> [u8; 3] {
+    match team {
+        "sharded" => [255, 211, 127],
+        "crux" => [242, 85, 85],
+        "malis" => [162, 124, 229],
+        "green" => [84, 214, 125],
+        "blue" => [108, 135, 253],
+        _ => [77, 78, 88],
+    }
+}
+
+/// What a tile gives, grouped (so `sand-water` is water), with ores over the floor.
+fn resource(t: &data::map::Tile) -> Option<&'static str> {
+    match t.ore.map(|x| x.name()) {
+        None | Some("spawn") => floor_resource(t.floor.name()),
+        Some(x) => x
+            .strip_prefix("wall-ore-")
+            .or(x.strip_prefix("ore-crystal-"))
+            .or(x.strip_prefix("ore-")),
+    }
+}
+
+/// What a floor gives in its own right, grouped like [`resource`].
+fn floor_resource(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "sand-floor" | "darksand" => "sand",
+        "shallow-water"
+        | "deep-water"
+        | "sand-water"
+        | "darksand-water"
+        | "tainted-water"
+        | "deep-tainted-water"
+        | "darksand-tainted-water" => "water",
+        "tar" => "oil",
+        "molten-slag" => "slag",
+        "spore-moss" => "spores",
+        _ => return None,
+    })
+}
+
+fn resource_color(r: &str) -> [u8; 3] {
+    match r {
+        "copper" => [217, 157, 115],
+        "lead" => [140, 127, 169],
+        "scrap" => [119, 119, 119],
+        "coal" => [80, 80, 80],
+        "titanium" => [141, 161, 227],
+        "thorium" => [249, 163, 199],
+        "beryllium" => [58, 143, 100],
+        "tungsten" => [118, 138, 154],
+        "sand" => [247, 203, 164],
+        "water" => [89, 110, 204],
+        "oil" => [150, 120, 70],
+        "slag" => [255, 161, 102],
+        "spores" => [125, 73, 166],
+        _ => [255, 255, 255],
+    }
+}
+
+/// For every tile, the densest resource around it (an index into the names) and its density, 0–1.
+fn heat(m: &Map) -> (Vec<&'static str>, Vec<Option<(usize, f32)>>) {
+    let (w, h) = (m.width, m.height);
+    let res = m.tiles.iter().map(resource).collect::<Vec<_>>();
+    let mut names = vec![];
+    for &r in res.iter().flatten() {
+        if !names.contains(&r) {
+            names.push(r);
+        }
+    }
+    let radius = (w.max(h) / 24).max(2);
+    let mut best = vec![None::<(usize, f32)>; w * h];
+    for (i, &name) in names.iter().enumerate() {
+        // summed area table, so each window is four lookups
+        let mut sat = vec![0u32; (w + 1) * (h + 1)];
+        for y in 0..h {
+            for x in 0..w {
+                sat[(y + 1) * (w + 1) + x + 1] = (res[y * w + x] == Some(name)) as u32
+                    + sat[y * (w + 1) + x + 1]
+                    + sat[(y + 1) * (w + 1) + x]
+                    - sat[y * (w + 1) + x];
+            }
+        }
+        for y in 0..h {
+            for x in 0..w {
+                let (x0, y0) = (x.saturating_sub(radius), y.saturating_sub(radius));
+                let (x1, y1) = ((x + radius + 1).min(w), (y + radius + 1).min(h));
+                let n = sat[y1 * (w + 1) + x1] + sat[y0 * (w + 1) + x0]
+                    - sat[y0 * (w + 1) + x1]
+                    - sat[y1 * (w + 1) + x0];
+                let d = n as f32 / ((x1 - x0) * (y1 - y0)) as f32;
+                if d > 0.0 && best[y * w + x].is_none_or(|(_, b)| d > b) {
+                    best[y * w + x] = Some((i, d));
+                }
+            }
+        }
+    }
+    (names, best)
+}
+
+/// Swatches and names, in the top left.
+fn legend(buf: &mut [u8], (w, h): (usize, usize), names: &[&str]) {
+    let k = match w {
+        ..300 => 1,
+        300..600 => 2,
+        _ => 3,
+    };
+    let bw = names.iter().map(|x| x.len() as i64).max().unwrap_or(0) * 4 * k + 10 * k;
+    let bh = names.len() as i64 * 7 * k + 2 * k;
+    let mut put = |x: i64, y: i64, c: [u8; 3]| {
+        if (0..w as i64).contains(&x) && (0..h as i64).contains(&y) {
+            let n = (y as usize * w + x as usize) * 3;
+            buf[n..n + 3].copy_from_slice(&c);
+        }
+    };
+    for y in 0..bh {
+        for x in 0..bw {
+            put(x, y, [0x1e; 3]);
+        }
+    }
+    for (i, name) in names.iter().enumerate() {
+        let y = 2 * k + i as i64 * 7 * k;
+        for dy in 0..5 * k {
+            for dx in 0..5 * k {
+                put(2 * k + dx, y + dy, resource_color(name));
+            }
+        }
+        super::font::text(name, (9 * k, y), k, None, &mut put);
+    }
+}
+
+/// Renders `m`, drawing only `o.layer`, cropped to `o.crop`.
+fn draw(mut m: Map, o: Options) -> fimg::Image<Vec<u8>, 3> {
+    let (width, height) = (m.width, m.height);
+    // what each tile is, for masking; computed before the builds go away
+    let tiles = m
+        .tiles
+        .iter()
+        .map(|t| {
+            (
+                t.ore.is_some_and(|x| x.name() != "spawn"),
+                t.build.as_ref().map(|b| team_color(&b.team.to_string())),
+            )
+        })
+        .collect::<Vec<_>>();
+    let heat = (o.layer == Layer::Resources).then(|| heat(&m));
+    if matches!(o.layer, Layer::Floor | Layer::Resources) {
+        for t in &mut m.tiles {
+            t.build = None;
+        }
+    }
     let i = m.render();
+    let (w, h) = (i.width() as usize, i.height() as usize);
+    let scale = (w / width.max(1)).max(1);
+    let mut buf = i.take_buffer();
+    if !matches!(o.layer, Layer::All | Layer::Floor) {
+        for (n, px) in buf.as_chunks_mut::<3>().0.iter_mut().enumerate() {
+            let (tx, ty) = ((n % w) / scale, height.saturating_sub((n / w) / scale + 1));
+            let Some(&(ore, build)) = tiles.get(ty * width + tx) else {
+                continue;
+            };
+            match (o.layer, build) {
+                (Layer::Resources, _) => {
+                    let (names, best) = heat.as_ref().unwrap();
+                    match best[ty * width + tx] {
+                        Some((r, d)) => {
+                            let v = (d * 2.0).min(1.0);
+                            for (x, c) in px.iter_mut().zip(resource_color(names[r])) {
+                                *x = (*x as f32 / 4.0 * (1.0 - v) + c as f32 * v) as u8;
+                            }
+                        }
+                        None => px.iter_mut().for_each(|x| *x /= 4),
+                    }
+                }
+                (Layer::Ores, _) if ore => (),
+                (Layer::Ores, _) => px.iter_mut().for_each(|x| *x /= 3),
+                (Layer::Buildings, None) | (Layer::Teams, None) => *px = [0; 3],
+                (Layer::Teams, Some(team)) => {
+                    for (x, t) in px.iter_mut().zip(team) {
+                        *x = ((*x as u16 + t as u16 * 2) / 3) as u8;
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+    let (w, h, mut buf) = crop(buf, (w, h), height, scale, o.crop);
+    if let Some((names, _)) = heat {
+        legend(&mut buf, (w, h), &names);
+    }
+    fimg::Image::build(w as u32, h as u32).buf(buf)
+}
+
+/// Crops a `w`×`h` render (of a map `height` tiles high, `scale` pixels a tile) to `(x, y, w, h)`, in tiles.
+fn crop(
+    buf: Vec<u8>,
+    (w, h): (usize, usize),
+    height: usize,
+    scale: usize,
+    crop: Option<(usize, usize, usize, usize)>,
+) -> (usize, usize, Vec<u8>) {
+    let Some((x, y, cw, ch)) = crop else {
+        return (w, h, buf);
+    };
+    // to pixels, with y going down
+    let x0 = (x * scale).min(w);
+    let x1 = ((x + cw) * scale).min(w);
+    let y0 = (height.saturating_sub(y + ch) * scale).min(h);
+    let y1 = (height.saturating_sub(y) * scale).min(h);
+    if x0 == x1 || y0 == y1 {
+        return (w, h, buf);
+    }
+    let mut out = Vec::with_capacity((x1 - x0) * (y1 - y0) * 3);
+    for row in y0..y1 {
+        out.extend_from_slice(&buf[(row * w + x0) * 3..(row * w + x1) * 3]);
+    }
+    (x1 - x0, y1 - y0, out)
+}
+
+fn render(m: Map, deser_took: Duration, o: Options) -> (Timings, Vec<u8>) {
+    let render_took = Instant::now();
+    let i = draw(m, o);
     let render_took = render_took.elapsed();
     let compression_took = Instant::now();
     let png = super::png(i);
@@ -120,45 +411,45 @@ pub async fn find(
 ) -> Result<Option<(String, (Map, Box<[u8]>), Duration)>> {
     match scour(msg).await? {
         None => Ok(None),
-        Some((Err(e), _)) => {
+        Some((Err(e), _, _)) => {
             msg.reply(c, string(e)).await?;
             Ok(None)
         }
-        Some((Ok(m), deser_took)) => Ok(Some((
-            msg.author_nick(c).await.unwrap_or(msg.author.name.clone()),
-            m,
-            deser_took,
-        ))),
+        Some((Ok(m), deser_took, ignored)) => {
+            if ignored != 0 {
+                use crate::emoji::named::*;
+                msg.reply(c, format!("{WARNING} only the first map in the archive is shown; {ignored} more were ignored"))
+                    .await?;
+            }
+            Ok(Some((
+                msg.author_nick(c).await.unwrap_or(msg.author.name.clone()),
+                m,
+                deser_took,
+            )))
+        }
     }
 }
 
-#[implicit_fn::implicit_fn]
 pub async fn with(msg: &Message, c: &serenity::client::Context) -> Result<()> {
     let Some((_auth, (m, b), deser_took)) = find(msg, c).await? else {
         return Ok(());
     };
     let t = msg.channel_id.start_typing(&c.http);
-    let (png, embed) = match embed(m, deser_took).await {
+    let (png, embed) = match embed(m, deser_took, Options::default()).await {
         Ok(x) => x,
         Err(e) => {
             use crate::emoji::named::*;
+            use super::crashes;
 
-            BENDN
-                .send_files(
-                    &c,
-                    [CreateAttachment::bytes(b, "file.msch")],
-                    CreateMessage::new().content(format!(
-                        "<@696196765564534825> panic `{e}` in {}\n",
-                        msg.guild(&c.cache).map_or(
-                            msg.guild_id
-                                .map(_.get().to_string())
-                                .unwrap_or(format!("dms with {}", msg.author.name)),
-                            _.name.clone(),
-                        )
-                    )),
-                )
-                .await?;
-            msg.reply(c, format!("{CANCEL} there was an error while rendering this map.\nthis issue has been reported to bendn, who will hopefully take a look eventually."))
+            let (error, backtrace) = crashes::panicked(e);
+            let id = crashes::file(
+                Some((crashes::Kind::Map, &b)),
+                &error,
+                backtrace,
+                crashes::guild(c, msg.guild_id, &msg.author),
+                "map message input",
+            );
+            msg.reply(c, format!("{CANCEL} there was an error while rendering this map.\nit has been logged as crash #{id}."))
                 .await?;
             return Ok(());
         }
@@ -178,7 +469,145 @@ pub async fn with(msg: &Message, c: &serenity::client::Context) -> Result<()> {
     Ok(())
 }
 
-async fn embed(m: Map, deser_took: Duration) -> Result<(CreateAttachment, CreateEmbed), JoinError> {
+/// What a map reviewer would otherwise have to open the game for.
+struct Analysis {
+    /// (ore or resource floor, tiles)
+    resources: Vec<(&'static str, u32)>,
+    spawns: u32,
+    /// (team, x, y)
+    cores: Vec<(String, usize, usize)>,
+    rules: Vec<(&'static str, String)>,
+}
+
+fn analyze(m: &Map) -> Analysis {
+    let mut resources: Vec<(&'static str, u32)> = vec![];
+    let mut count = |x: &'static str| match resources.iter_mut().find(|(n, _)| *n == x) {
+        Some((_, n)) => *n += 1,
+        None => resources.push((x, 1)),
+    };
+    let mut spawns = 0;
+    let mut cores = vec![];
+    for (i, t) in m.tiles.iter().enumerate() {
+        match t.ore.map(|x| x.name()) {
+            Some("spawn") => spawns += 1,
+            Some(x) => count(x),
+            None => (),
+        }
+        if let Some(x) = floor_resource(t.floor.name()) {
+            count(x);
+        }
+        if let Some(b) = &t.build
+            && b.block.name().starts_with("core-")
+        {
+            cores.push((b.team.to_string(), i % m.width, i / m.width));
+        }
+    }
+    resources.sort_by(|a, b| b.1.cmp(&a.1));
+    Analysis {
+        resources,
+        spawns,
+        cores,
+        rules: m.tags.get("rules").map(|r| rules(r)).unwrap_or_default(),
+    }
+}
+
+/// Resource tiles by region (a 3×3 grid), richest first, and around each core.
+fn richness(m: &Map, cores: &[(String, usize, usize)]) -> (String, String) {
+    const NAMES: [[&str; 3]; 3] = [
+        ["bottom left", "bottom", "bottom right"],
+        ["left", "center", "right"],
+        ["top left", "top", "top right"],
+    ];
+    // how far from a core counts as near it, in tiles
+    const NEAR: usize = 25;
+    fn top(c: &[(&'static str, u32)]) -> String {
+        let mut c = c.to_vec();
+        c.sort_by(|a, b| b.1.cmp(&a.1));
+        c.iter()
+            .take(3)
+            .map(|(n, k)| format!("{n} {k}"))
+            .intersperse(", ".to_string())
+            .collect()
+    }
+    fn add(c: &mut Vec<(&'static str, u32)>, r: &'static str) {
+        match c.iter_mut().find(|(n, _)| *n == r) {
+            Some((_, k)) => *k += 1,
+            None => c.push((r, 1)),
+        }
+    }
+    let (w, h) = (m.width.max(1), m.height.max(1));
+    let mut regions = vec![vec![]; 9];
+    let mut near = vec![vec![]; cores.len()];
+    for (i, t) in m.tiles.iter().enumerate() {
+        let Some(r) = resource(t) else { continue };
+        let (x, y) = (i % w, i / w);
+        add(&mut regions[(y * 3 / h) * 3 + x * 3 / w], r);
+        for (n, (_, cx, cy)) in cores.iter().enumerate() {
+            if x.abs_diff(*cx).pow(2) + y.abs_diff(*cy).pow(2) <= NEAR * NEAR {
+                add(&mut near[n], r);
+            }
+        }
+    }
+    let area = ((w / 3).max(1) * (h / 3).max(1)) as f32;
+    let mut order = (0..9).collect::<Vec<_>>();
+    let total = |n: usize| regions[n].iter().map(|(_, k)| k).sum::<u32>();
+    order.sort_by_key(|&n| std::cmp::Reverse(total(n)));
+    let mut out = String::new();
+    for n in order.into_iter().filter(|&n| total(n) != 0) {
+        writeln!(
+            out,
+            "{}: {:.0}% ({})",
+            NAMES[n / 3][n % 3],
+            (total(n) as f32 / area * 100.0).min(100.0),
+            top(&regions[n])
+        )
+        .unwrap();
+    }
+    if out.is_empty() {
+        out.push_str("no resources");
+    }
+    let mut c = String::new();
+    for ((team, x, y), near) in cores.iter().zip(&near).take(8) {
+        writeln!(c, "{team} ({x}, {y}): {}", top(near)).unwrap();
+    }
+    (out, c)
+}
+
+/// Pulls the interesting keys out of the rules tag.
+fn rules(r: &str) -> Vec<(&'static str, String)> {
+    let Some(r) = super::waves::json(r) else {
+        return vec![("rules", "unreadable".to_string())];
+    };
+    let on = |k: &str| r.get(k).and_then(|x| x.as_bool());
+    let mut v = vec![];
+    let waves = on("waves") == Some(true);
+    v.push(("waves", if waves { "yes" } else { "no" }.to_string()));
+    if waves {
+        if let Some(t) = r.get("waveSpacing").and_then(|x| x.as_f64()) {
+            // ticks
+            v.push(("wave spacing", format!("{:.0}s", t / 60.0)));
+        }
+        let timer = on("waveTimer").unwrap_or(true);
+        v.push(("wave timer", if timer { "yes" } else { "no" }.to_string()));
+        let win = r.get("winWave").and_then(|x| x.as_u64());
+        if let Some(w) = win.filter(|&x| x != 0) {
+            v.push(("win wave", w.to_string()));
+        }
+    }
+    if on("attackMode") == Some(true) {
+        v.push(("attack mode", "yes".to_string()));
+    }
+    if on("pvp") == Some(true) {
+        v.push(("pvp", "yes".to_string()));
+    }
+    v
+}
+
+pub(super) async fn embed(
+    m: Map,
+    deser_took: Duration,
+    o: Options,
+) -> Result<(CreateAttachment, CreateEmbed), JoinError> {
     let name = strip_colors(m.tags.get("name").or(m.tags.get("mapname")).unwrap());
     let d = strip_colors(m.tags.get("description").map(|x| &**x).unwrap_or("?"));
     let f = if m.width == m.height {
@@ -186,49 +615,170 @@ async fn embed(m: Map, deser_took: Duration) -> Result<(CreateAttachment, Create
     } else {
         format!("{}×{}", m.height, m.width)
     };
-    let (timings, png) = tokio::task::spawn_blocking(move || render(m, deser_took)).await?;
-    Ok((
-        CreateAttachment::bytes(png, "map.png"),
-        CreateEmbed::new()
-            .title(&name)
-            .description(d)
-            .footer(CreateEmbedFooter::new(format!(
-                "render of {name} ({f}) took: {:.3}s",
-                timings.total.as_secs_f64()
-            )))
-            .attachment("map.png")
-            .color(SUCCESS),
-    ))
+    let (a, rich, (timings, png)) = tokio::task::spawn_blocking(move || {
+        let a = analyze(&m);
+        let rich = (o.layer == Layer::Resources).then(|| richness(&m, &a.cores));
+        (a, rich, render(m, deser_took, o))
+    })
+    .await?;
+    let mut e = CreateEmbed::new()
+        .title(&name)
+        .description(d)
+        .footer(CreateEmbedFooter::new(format!(
+            "render of {name} ({f}) took: {:.3}s",
+            timings.total.as_secs_f64()
+        )))
+        .attachment("map.png")
+        .color(SUCCESS);
+    if !a.resources.is_empty() {
+        e = e.field(
+            "resources",
+            a.resources
+                .iter()
+                .take(12)
+                .map(|(n, c)| format!("`{n}`: {c}"))
+                .intersperse("\n".to_string())
+                .collect::<String>(),
+            true,
+        );
+    }
+    if !a.cores.is_empty() {
+        let mut cores = String::new();
+        for (team, x, y) in a.cores.iter().take(12) {
+            writeln!(cores, "{team}: ({x}, {y})").unwrap();
+        }
+        e = e.field("cores", cores, true);
+    }
+    if a.spawns != 0 {
+        e = e.field("enemy spawns", a.spawns.to_string(), true);
+    }
+    if !a.rules.is_empty() {
+        e = e.field(
+            "rules",
+            a.rules
+                .iter()
+                .map(|(k, v)| format!("{k}: {v}"))
+                .intersperse("\n".to_string())
+                .collect::<String>(),
+            true,
+        );
+    }
+    if let Some((regions, cores)) = rich {
+        e = e.field("richest regions", regions, true);
+        if !cores.is_empty() {
+            e = e.field("near cores", cores, true);
+        }
+    }
+    Ok((CreateAttachment::bytes(png, "map.png"), e))
 }
 
+/// The last map options each user rendered with.
+pub static OPTIONS: super::Remembered<Options> = super::Remembered::new();
+
 #[poise::command(
     context_menu_command = "Render map",
     install_context = "User",
     interaction_context = "Guild|PrivateChannel"
 )]
-/// Renders map inside a message.
+/// Renders map inside a message, with the layer and crop of your last `/render_file` (within the hour).
 pub async fn render_message(c: super::Context<'_>, m: Message) -> Result<()> {
     super::log(&c);
     let Some((_auth, (m, b), deser_took)) = find(&m, c.serenity_context()).await? else {
         poise::say_reply(c, "no map").await?;
         return Ok(());
     };
-    let (png, embed) = match embed(m, deser_took).await {
+    let remembered = OPTIONS.get(c.author().id);
+    let o = remembered.unwrap_or_default();
+    let (png, embed) = match embed(m, deser_took, o).await {
         Ok(x) => x,
         Err(e) => {
-            BENDN
-                .send_files(
-                    &c,
-                    [CreateAttachment::bytes(b, "map.msav")],
-                    CreateMessage::new().content(format!("<@696196765564534825>　failure: {e}")),
-                )
-                .await?;
-            c.say("there was a problem. i have notified bendn about this issue.")
+            let id = crash(c, &b, e);
+            c.say(format!("there was a problem. it has been logged as crash #{id}."))
                 .await?;
             return Ok(());
         }
     };
 
-    poise::send_reply(c, CreateReply::default().attachment(png).embed(embed)).await?;
+    let mut r = CreateReply::default().attachment(png).embed(embed);
+    // so it's clear where the layer and crop came from
+    if remembered.is_some() {
+        r = r.content("-# with the layer and crop of your last `/render_file`");
+    }
+    poise::send_reply(c, r).await?;
+    Ok(())
+}
+
+/// Copies every building whose footprint lies inside `(x, y, w, h)` into a schematic.
+/// Buildings a schematic can't hold are left out.
+pub fn extract(m: &Map, (x, y, w, h): (usize, usize, usize, usize)) -> Schematic {
+    let (w, h) = (w.min(m.width.saturating_sub(x)), h.min(m.height.saturating_sub(y)));
+    let mut s = Schematic::new(w, h);
+    for (i, t) in m.tiles.iter().enumerate() {
+        let Some(b) = &t.build else { continue };
+        let (tx, ty) = (i % m.width, i / m.width);
+        let size = b.block.get_size() as usize;
+        let off = (size - 1) / 2;
+        if tx < x + off || ty < y + off || tx + size - off > x + w || ty + size - off > y + h {
+            continue;
+        }
+        let config = b
+            .state
+            .as_ref()
+            .and_then(|st| b.block.serialize_state(st).ok())
+            .unwrap_or(data::dynamic::DynData::Empty);
+        _ = s.set(tx - x, ty - y, b.block, config, b.rotation);
+    }
+    let name = m.tags.get("name").or(m.tags.get("mapname")).unwrap();
+    s.tags
+        .insert("name".to_string(), format!("{name} ({x}, {y})"));
+    s
+}
+
+#[poise::command(
+    slash_command,
+    install_context = "User|Guild",
+    interaction_context = "Guild|BotDm|PrivateChannel"
+)]
+/// Cut a schematic out of a map.
+pub async fn map_extract(
+    c: super::Context<'_>,
+    #[description = "map, msav"] map: Attachment,
+    #[description = "region: x, y, w, h (in tiles, from the bottom left)"] region: String,
+) -> Result<()> {
+    use crate::emoji::named::*;
+    super::log(&c);
+    c.defer().await?;
+    let region @ (x, y, ..) = match Options::parse(None, Some(&region)) {
+        Ok(Options { crop: Some(x), .. }) => x,
+        Ok(_) => unreachable!(),
+        Err(e) => return Ok(drop(c.say(format!("{CANCEL} {e}")).await?)),
+    };
+    let m = match download(&map).await? {
+        (Err(e), _) => return Ok(drop(c.say(string(e)).await?)),
+        (Ok((m, _)), _) => m,
+    };
+    if x >= m.width || y >= m.height {
+        c.say(format!(
+            "{CANCEL} ({x}, {y}) is outside the map, which is {}×{}",
+            m.width, m.height
+        ))
+        .await?;
+        return Ok(());
+    }
+    let s = tokio::task::spawn_blocking(move || extract(&m, region)).await?;
+    if s.block_iter().next().is_none() {
+        c.say(format!("{CANCEL} no buildings there")).await?;
+        return Ok(());
+    }
+    poise::send_reply(
+        c,
+        super::schematic::output(
+            super::schematic::Schem { schem: s },
+            &c.author().name,
+            &c.author().face(),
+        )
+        .await?,
+    )
+    .await?;
     Ok(())
 }
@@ -1,12 +1,27 @@
+mod archive;
+mod bom;
+mod crashes;
 mod data;
 mod db;
+mod debugger;
+mod diff;
+mod dupes;
+mod font;
+mod lint;
 mod logic;
 mod map;
 pub mod ownership;
+mod pack;
+mod processors;
+mod production;
+mod replace;
 pub mod repos;
 mod schematic;
 pub mod search;
 mod sorter;
+mod transform;
+mod waves;
+mod world;
 use charts_rs::{Series, THEME_GRAFANA};
 pub use data::log;
 
@@ -155,6 +170,11 @@ pub async fn scour(
                         }).unwrap_or(AIR_FACTORY)])
                     } else { tags(tg) };
                     x.schem.tags.insert("labels".into(), tags.clone());
+                    let dupe = dupes::find(repo, &x, msg.id.get());
+                    if let Some(dupes::Dupe::Exact(_)) = dupe {
+                        continue;
+                    }
+                    dupes::insert(repo, &x, dupes::Post { channel: ch.get(), message: msg.id.get() });
                     let who = msg.author_nick(c).await.unwrap_or(msg.author.name.clone());
                     ownership::get(repo)
                         .await
@@ -162,6 +182,13 @@ pub async fn scour(
                     repo.write(d, msg.id, x);
                     repo.commit(&who, msg.author.id, &format!("add {:x}.msch", msg.id.get()));
                     msg.react(c, emojis::get!(MERGE)).await?;
+                    if let Some(dupes::Dupe::Near(p)) = dupe {
+                        let link = format!("https://discord.com/channels/{}/{}/{}", c.guild_id().map_or(0, Into::into), p.channel, p.message);
+                        msg.channel_id.send_message(c, CreateMessage::new()
+                            .reference_message(&msg)
+                            .allowed_mentions(CreateAllowedMentions::new().all_roles(true).all_users(true))
+                            .content(format!("{} {} this matches {link} except for its name or tags", emoji::named::WARNING, repo.mention_admins()))).await?;
+                    }
                     n += 1;
                 }
             }
@@ -191,6 +218,7 @@ async fn del(
     use crate::emoji::named::*;
     if let Ok(s) = git.schem(dir, deleted_message_id.into()) {
         let own = git.own().await.erase(deleted_message_id).unwrap();
+        dupes::remove(git, &s, deleted_message_id);
         git.remove(dir, deleted_message_id.into());
         git.commit("plent", 0u64, &format!("remove {deleted_message_id:x}"));
         git.push();
@@ -257,6 +285,9 @@ async fn handle_message(
         .unwrap_or(new_message.author.name.clone());
     let post = EXTRA.get(&new_message.channel_id.get()).map(|x| x.clone());
     let (dir, l, repo) = sep(SPECIAL.get(&new_message.channel_id.get()).or(post.as_ref()));
+    if new_message.attachments.iter().any(archive::is) {
+        return archive::with(c, new_message, &who, l, dir.zip(repo)).await;
+    }
     let m = Msg {
         author: who.clone(),
         locale: new_message
@@ -293,8 +324,33 @@ async fn handle_message(
             new_message.delete(c).await?;
             return Ok(());
         }
+        // the repo keeps a file per message, named after it
+        ControlFlow::Break((_, _, s)) if s.len() > 1 && dir.is_some() && repo.is_some() => {
+            new_message.reply(c, format!("{} one schematic per message here, please. to add several, upload them as a zip.", emoji::named::CANCEL)).await?;
+            return Ok(());
+        }
         ControlFlow::Break((ha, m, s)) => {
+            let link = |p: dupes::Post| format!(
+                "https://discord.com/channels/{}/{}/{}",
+                new_message.guild_id.map_or(0, Into::into), p.channel, p.message
+            );
+            // before posting, so a refused duplicate leaves no render or thread behind
+            let dupe = dir.zip(repo).zip(s.first()).and_then(|((_, repo), s)| dupes::find(repo, s, new_message.id.get()));
+            if let Some(dupes::Dupe::Exact(p)) = dupe {
+                new_message.reply(c, format!("{} this is already in the repo: {}", emoji::named::CANCEL, link(p))).await?;
+                return Ok(());
+            }
             let (m, n, s) = schematic::send(m,c,s).await?;
+            if db::lints(m.channel_id.get()) {
+                for schematic::Schem { schem: s } in &s {
+                    if let Some((a, e)) = lint::report(s).await? {
+                        let name = emoji::mindustry::to_discord(&strip_colors(s.tags.get("name").unwrap()));
+                        m.channel_id
+                            .send_message(c, CreateMessage::new().reference_message(&m).add_file(a).embed(e.title(name)))
+                            .await?;
+                    }
+                }
+            }
             if SPECIAL.contains_key(&m.channel_id.get()) || THREADED.contains(&m.channel_id.get()) {
                 m.channel_id
                     .create_thread_from_message(
@@ -307,10 +363,19 @@ async fn handle_message(
                     .await
                     .unwrap();
             }
+            // one schematic, as more were refused above
             if let Some(dir) = dir
                 && let Some(repo) = repo
+                && let Ok([s]) = <[schematic::Schem; 1]>::try_from(s)
             {
                 println!("adding {dir}");
+                if let Some(dupes::Dupe::Near(p)) = dupe {
+                    m.channel_id.send_message(c, CreateMessage::new()
+                        .reference_message(&m)
+                        .allowed_mentions(CreateAllowedMentions::new().all_roles(true).all_users(true))
+                        .content(format!("{} {} this matches {} except for its name or tags", emoji::named::WARNING, repo.mention_admins(), link(p)))).await?;
+                }
+                dupes::insert(repo, &s, dupes::Post { channel: new_message.channel_id.get(), message: new_message.id.get() });
                 // add :)
                 repo.own().await.insert(
                     new_message.id.get(),
@@ -353,6 +418,7 @@ impl Bot {
     pub async fn spawn() {
         use emoji::named::*;
         println!("bot startup");
+        crashes::hook();
         let tok =
             std::env::var("TOKEN").unwrap_or_else(|_| read_to_string("token").expect("wher token"));
         let f = poise::Framework::builder()
@@ -361,6 +427,7 @@ impl Bot {
                     logic::run(),
                     lb(),
                     logic::run_file(),
+                    debugger::debug(),
                     sorter::sorter(),
                     sorter::mapper(),
                     schembrowser_instructions(),
@@ -370,12 +437,26 @@ impl Bot {
                     scour(),
                     search::search(),
                     search::file(),
-                    rename(),
-                    rename_file(),
+                    edit_meta(),
                     render(),
                     render_file(),
                     render_message(),
                     map::render_message(),
+                    diff::diff(),
+                    transform::transform(),
+                    replace::swap(),
+                    bom::bom(),
+                    lint::lint(),
+                    lint::lint_channel(),
+                    processors::processors(),
+                    pack::pack_block(),
+                    pack::pack_file(),
+                    dupes::reindex(),
+                    map::map_extract(),
+                    waves::waves(),
+                    crashes::crash_list(),
+                    crashes::crash_rerun(),
+                    crashes::crash_resolve(),
                     stats(),
                     retag()
                 ],
@@ -435,6 +516,7 @@ impl Bot {
                                     _ = db::remove(channel_id.get());
                                     let who = nick.as_deref().unwrap_or(&user.name);
                                     let own = ownership::get(git).await.erase(*message_id).unwrap();
+                                    dupes::remove(git, &s, message_id.get());
                                     git.remove(dir, *message_id);
                                     git.commit(who,  m.author.id, &format!("remove {:x}.msch", message_id.get()));
                                     git.push();
@@ -497,19 +579,52 @@ impl Bot {
                                         l
                                     )
                                     .await? && ha != hash {
+                                        let in_repo = dir.zip(repo).filter(|&(dir, git)| git.has(dir, *id));
+                                        // checked against the whole set before anything is rendered, as the repo keeps one file per message
+                                        if v.len() > 1 && dir.is_some() && repo.is_some() {
+                                            d.tracker.insert(*id, (hash, r));
+                                            channel_id.say(c, format!("{} one schematic per message here, please.{}", emoji::named::CANCEL, if in_repo.is_some() { " the repo keeps the old one." } else { "" })).await?;
+                                            return Ok(());
+                                        }
+                                        let link = |p: dupes::Post| format!("https://discord.com/channels/{}/{}/{}", r.guild_id.map_or(0, Into::into), p.channel, p.message);
+                                        let dupe = in_repo.zip(v.first()).and_then(|((_, git), s)| dupes::find(git, s, id.get()));
+                                        if let Some(dupes::Dupe::Exact(p)) = dupe {
+                                            let say = format!("{} this is already in the repo: {}. the repo keeps the old one.", emoji::named::CANCEL, link(p));
+                                            d.tracker.insert(*id, (hash, r));
+                                            channel_id.say(c, say).await?;
+                                            return Ok(());
+                                        }
                                         _ = r.delete(c).await;
                                         let (m, _, s) = schematic::send(m,c, v).await?;
+                                        if let Some(dupes::Dupe::Near(p)) = dupe && let Some(git) = repo {
+                                            channel_id.send_message(c, CreateMessage::new()
+                                                .reference_message(&m)
+                                                .allowed_mentions(CreateAllowedMentions::new().all_roles(true).all_users(true))
+                                                .content(format!("{} {} this now matches {} except for its name or tags", emoji::named::WARNING, git.mention_admins(), link(p)))).await?;
+                                        }
                                         d.tracker.insert(*id, (ha, m));
-                                        if let Some(dir) = dir && let Some(git) = repo && git.has(dir, *id) {
+                                        if let Some((dir, git)) = in_repo && let Ok([s]) = <[schematic::Schem; 1]>::try_from(s) {
                                             // update :)
                                             if *guild_id == 925674713429184564 && !cfg!(debug_assertions) {
-                                            send(c,|x| x
-                                                .avatar_url(author.face())
-                                                .username(&who)
-                                                .embed(CreateEmbed::new().color(AD)
-                                                    .description(format!("https://discord.com/channels/925674713429184564/{channel_id}/{id} {ROTATE} update {} (`{:x}.msch`)", emoji::mindustry::to_discord(&strip_colors(s.tags.get("name").unwrap())), id.get())))
-                                            ).await;
+                                            let changes = match git.schem(dir, *id) {
+                                                Ok(old) => Some(diff::image(schematic::Schem { schem: old }, schematic::Schem { schem: s.clone() }).await?),
+                                                Err(_) => None,
+                                            };
+                                            send(c,|x| {
+                                                let mut e = CreateEmbed::new().color(AD)
+                                                    .description(format!("https://discord.com/channels/925674713429184564/{channel_id}/{id} {ROTATE} update {} (`{:x}.msch`)", emoji::mindustry::to_discord(&strip_colors(s.tags.get("name").unwrap())), id.get()));
+                                                let mut x = x.avatar_url(author.face()).username(&who);
+                                                if let Some((changes, png)) = changes && !changes.is_empty() {
+                                                    e = e.field("changes", changes.summary(), false).image("attachment://diff.png");
+                                                    x = x.add_file(CreateAttachment::bytes(png, "diff.png"));
+                                                }
+                                                x.embed(e)
+                                            }).await;
                                         }
+                                            if let Ok(old) = git.schem(dir, *id) {
+                                                dupes::remove(git, &old, id.get());
+                                            }
+                                            dupes::insert(git, &s, dupes::Post { channel: channel_id.get(), message: id.get() });
                                             git.write(dir, *id, s);
                                             git.commit(&who, author.id, &format!("update {:x}.msch", id.get()));
                                             git.push();
@@ -561,11 +676,20 @@ impl Bot {
                             schembrowser_instructions(),
                             render_file(),
                             render_message(),
-                            rename(),
-                            rename_file(),
+                            edit_meta(),
                             stats(),
                             map::render_message(),
+                            diff::diff(),
+                            transform::transform(),
+                            replace::swap(),
+                            bom::bom(),
+                            lint::lint(),
+                            processors::processors(),
+                            pack::pack_file(),
+                            map::map_extract(),
+                            waves::waves(),
                             logic::run_file(),
+                            debugger::debug(),
                             sorter::sorter(),
                             sorter::mapper(),
                         ],
@@ -573,7 +697,7 @@ impl Bot {
                     .await?;
                     poise::builtins::register_in_guild(
                         ctx,
-                        &[search::search(), lb(), lb_no_vds(), search::file(), retag()],
+                        &[search::search(), lb(), lb_no_vds(), search::file(), retag(), lint::lint_channel(), dupes::reindex(), crashes::crash_list(), crashes::crash_rerun(), crashes::crash_resolve()],
                         925674713429184564.into(),
                     )
                     .await?;
@@ -819,9 +943,18 @@ pub mod emojis {
 type Context<'a> = poise::Context<'a, Data, anyhow::Error>;
 
 async fn on_error(error: poise::FrameworkError<'_, Data, anyhow::Error>) {
-    use poise::FrameworkError::Command;
+    use poise::FrameworkError::{Command, CommandPanic};
     match error {
         Command { error, ctx, .. } => {
+            // only crashes caught inside the command are filed; ordinary errors are just told to the user
+            if let Some(&crashes::Filed(id)) = error.downcast_ref::<crashes::Filed>() {
+                let guild = crashes::guild(ctx.serenity_context(), ctx.guild_id(), ctx.author());
+                crashes::annotate(id, guild, &ctx.command().qualified_name);
+                ctx.say(format!("there was a problem. it has been logged as crash #{id}."))
+                    .await
+                    .unwrap();
+                return;
+            }
             let mut msg;
             {
                 let mut chain = error.chain();
@@ -834,26 +967,21 @@ async fn on_error(error: poise::FrameworkError<'_, Data, anyhow::Error>) {
                     }
                 }
             }
-            /* let bt = error.backtrace();
-            if bt.status() == std::backtrace::BacktraceStatus::Captured {
-                let parsed = btparse_stable::deserialize(error.backtrace()).unwrap();
-                let mut s = vec![];
-                for frame in parsed.frames {
-                    if let Some(line) = frame.line
-                        && (frame.function.contains("plent")
-                            || frame.function.contains("poise")
-                            || frame.function.contains("serenity")
-                            || frame.function.contains("mindus")
-                            || frame.function.contains("image"))
-                    {
-                        s.push(format!("l{}@{}", line, frame.function));
-                    }
-                }
-                s.truncate(15);
-                write!(msg, "trace: ```rs\n{}\n```", s.join("\n")).unwrap();
-            } */
             ctx.say(msg).await.unwrap();
         }
+        CommandPanic { payload, ctx, .. } => {
+            let error = payload.as_deref().unwrap_or("panic");
+            let id = crashes::file(
+                None,
+                error,
+                crashes::backtrace(error),
+                crashes::guild(ctx.serenity_context(), ctx.guild_id(), ctx.author()),
+                &ctx.command().qualified_name,
+            );
+            ctx.say(format!("there was a problem. it has been logged as crash #{id}."))
+                .await
+                .unwrap();
+        }
         err => poise::builtins::on_error(err).await.unwrap(),
     }
 }
@@ -911,13 +1039,16 @@ pub async fn help(
     Ok(())
 }
 
-pub fn png(p: fimg::Image<Vec<u8>, 3>) -> Vec<u8> {
+pub fn png<const C: usize>(p: fimg::Image<Vec<u8>, C>) -> Vec<u8> {
     use oxipng::*;
     let p = RawImage::new(
         p.width(),
         p.height(),
-        ColorType::RGB {
-            transparent_color: None,
+        match C {
+            4 => ColorType::RGBA,
+            _ => ColorType::RGB {
+                transparent_color: None,
+            },
         },
         BitDepth::Eight,
         p.take_buffer(),
@@ -976,12 +1107,25 @@ pub async fn ping(c: Context<'_>) -> Result<()> {
     interaction_context = "Guild|BotDm|PrivateChannel"
 )]
 /// Renders base64 schematic.
-pub async fn render(c: Context<'_>, #[description = "schematic, base64"] s: String) -> Result<()> {
+pub async fn render(
+    c: Context<'_>,
+    #[description = "schematic, base64"] s: String,
+    #[description = "output scale (0.25–4)"] scale: Option<f32>,
+    #[description = "background: transparent, or a hex color like #1e1e2e"] background: Option<
+        String,
+    >,
+    #[description = "draw a tile grid (a brighter, numbered line every 5 tiles)"] grid: Option<bool>,
+    #[description = "hide configs, such as sorter items"] hide_configs: Option<bool>,
+) -> Result<()> {
     log(&c);
+    let style = match style(c, scale, background, grid, hide_configs) {
+        Ok(x) => x,
+        Err(e) => return Ok(drop(poise::say_reply(c, e).await?)),
+    };
     poise::send_reply(
         c,
         match schematic::from_b64(&s) {
-            Ok(s) => schematic::reply(s, &c.author().name, &c.author().face()).await?,
+            Ok(s) => schematic::reply(vec![s], &c.author().name, &c.author().face(), style).await?,
             Err(e) => CreateReply::default().content(format!("schem broken / not schem: {e}")),
         },
     )
@@ -998,12 +1142,35 @@ pub async fn render(c: Context<'_>, #[description = "schematic, base64"] s: Stri
 pub async fn render_file(
     c: Context<'_>,
     #[description = "map / schematic, msch"] s: Attachment,
+    #[description = "output scale (0.25–4)"] scale: Option<f32>,
+    #[description = "background: transparent, or a hex color like #1e1e2e"] background: Option<
+        String,
+    >,
+    #[description = "draw a tile grid (a brighter, numbered line every 5 tiles)"] grid: Option<bool>,
+    #[description = "hide configs, such as sorter items"] hide_configs: Option<bool>,
+    #[description = "maps: which layer to draw"] layer: Option<map::Layer>,
+    #[description = "maps: crop to x, y, w, h (in tiles, from the bottom left)"] crop: Option<
+        String,
+    >,
 ) -> Result<()> {
     log(&c);
     _ = c.defer().await;
+    let style = match style(c, scale, background, grid, hide_configs) {
+        Ok(x) => x,
+        Err(e) => return Ok(drop(poise::say_reply(c, e).await?)),
+    };
+    let o = match map::Options::parse(layer, crop.as_deref()) {
+        Ok(x) => x,
+        Err(e) => return Ok(drop(poise::say_reply(c, e).await?)),
+    };
+    if layer.is_some() || o.crop.is_some() {
+        map::OPTIONS.set(c.author().id, o);
+    } else {
+        map::OPTIONS.forget(c.author().id);
+    }
 
     let Some(s) = schematic::from_attachments(std::slice::from_ref(&s)).await? else {
-        match map::reply(c, &s).await? {
+        match map::reply(c, &s, o).await? {
             ControlFlow::Break(x) => return Ok(drop(poise::send_reply(c, x).await?)),
             ControlFlow::Continue(e) if e != "not a map." => {
                 return Ok(drop(poise::say_reply(c, e).await?));
@@ -1021,58 +1188,254 @@ pub async fn render_file(
     };
     poise::send_reply(
         c,
-        schematic::reply(s, &c.author().name, &c.author().face()).await?,
+        schematic::reply(vec![s], &c.author().name, &c.author().face(), style).await?,
     )
     .await?;
     Ok(())
 }
 
-#[poise::command(slash_command)]
-/// Rename a schematic.
-async fn rename_file(
+/// Settings each user last rendered with, which the context menus (having no options) reuse.
+/// Forgotten after an hour, and bounded, so a render doesnt hinge on a command from days ago.
+pub struct Remembered<V>(LazyLock<DashMap<UserId, (Instant, V)>>);
+
+impl<V: Copy> Remembered<V> {
+    const FOR: Duration = Duration::from_secs(60 * 60);
+    const MAX: usize = 1024;
+
+    pub const fn new() -> Self {
+        Self(LazyLock::new(DashMap::new))
+    }
+
+    pub fn get(&self, u: UserId) -> Option<V> {
+        self.0
+            .get(&u)
+            .filter(|x| x.0.elapsed() < Self::FOR)
+            .map(|x| x.1)
+    }
+
+    pub fn set(&self, u: UserId, v: V) {
+        if self.0.len() >= Self::MAX {
+            self.0.retain(|_, (at, _)| at.elapsed() < Self::FOR);
+        }
+        if self.0.len() >= Self::MAX {
+            // the iterator locks its shard, so it's done with before removing
+            let oldest = self.0.iter().min_by_key(|x| x.0).map(|x| *x.key());
+            if let Some(oldest) = oldest {
+                self.0.remove(&oldest);
+            }
+        }
+        self.0.insert(u, (Instant::now(), v));
+    }
+
+    pub fn forget(&self, u: UserId) {
+        self.0.remove(&u);
+    }
+}
+
+/// The last style each user rendered with.
+static STYLES: Remembered<schematic::Style> = Remembered::new();
+
+fn style(
     c: Context<'_>,
-    #[description = "schematic, msch"] s: Attachment,
-    #[description = "new name"] name: String,
-) -> Result<()> {
-    log(&c);
-    let Some(schematic::Schem { schem: mut s }) =
-        schematic::from_attachments(std::slice::from_ref(&s)).await?
-    else {
-        c.reply("no schem!").await?;
-        return Ok(());
+    scale: Option<f32>,
+    background: Option<String>,
+    grid: Option<bool>,
+    hide_configs: Option<bool>,
+) -> Result<schematic::Style, String> {
+    let s = schematic::Style {
+        scale: scale.unwrap_or(1.0).clamp(0.25, 4.0),
+        background: background.map(|x| x.parse()).transpose()?,
+        grid: grid.unwrap_or(false),
+        configs: !hide_configs.unwrap_or(false),
     };
-    s.tags.insert("name".to_string(), name);
-    let mut o = DataWrite::default();
-    s.serialize(&mut o)?;
-    poise::send_reply(
-        c,
-        CreateReply::default().attachment(CreateAttachment::bytes(o.consume(), "out.msch")),
-    )
-    .await?;
-    Ok(())
+    if s.is_default() {
+        STYLES.forget(c.author().id);
+    } else {
+        STYLES.set(c.author().id, s);
+    }
+    Ok(s)
 }
 
-#[poise::command(slash_command)]
-/// Rename a schematic.
-async fn rename(
-    c: Context<'_>,
-    #[description = "schematic, base64"] s: String,
-    #[description = "new name"] name: String,
+#[derive(poise::Modal)]
+#[name = "Edit schematic"]
+struct Meta {
+    #[max_length = 256]
+    #[placeholder = "left empty, the name stays"]
+    name: Option<String>,
+    #[paragraph]
+    #[max_length = 2000]
+    description: Option<String>,
+    #[placeholder = "graphite, silicon or [\"graphite\",\"silicon\"]"]
+    labels: Option<String>,
+}
+
+impl Meta {
+    fn of(s: &mindus::Schematic) -> Self {
+        Self {
+            name: s.tags.get("name").cloned(),
+            description: s.tags.get("description").cloned(),
+            labels: s.tags.get("labels").cloned(),
+        }
+    }
+
+    /// With `keep`, empty fields leave the tag alone (the modal had nothing filled in), rather than removing it.
+    fn apply(self, s: &mut mindus::Schematic, keep: bool) -> Result<(), String> {
+        let labels = self.labels.map(|l| match l.trim() {
+            l if l.starts_with('[') => l.to_string(),
+            l => tags(
+                &l.split(',')
+                    .map(str::trim)
+                    .filter(|x| !x.is_empty())
+                    .collect::<Vec<_>>(),
+            ),
+        });
+        if let Some(l) = &labels
+            && !schematic::valid_tags(l)
+        {
+            return Err(format!(
+                "{} labels `{l}` are malformed",
+                emoji::named::CANCEL
+            ));
+        }
+        if let Some(n) = self.name {
+            s.tags.insert("name".to_string(), n);
+        }
+        for (k, v) in [("description", self.description), ("labels", labels)] {
+            match v {
+                Some(v) => _ = s.tags.insert(k.to_string(), v),
+                None if !keep => _ = s.tags.remove(k),
+                None => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn meta_buttons() -> Vec<CreateActionRow> {
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new("edit")
+            .label("edit again")
+            .style(ButtonStyle::Secondary),
+        CreateButton::new("done")
+            .label("done")
+            .style(ButtonStyle::Success),
+    ])]
+}
+
+#[poise::command(
+    slash_command,
+    install_context = "User|Guild",
+    interaction_context = "Guild|BotDm|PrivateChannel"
+)]
+/// Edit a schematic's name, description and labels.
+async fn edit_meta(
+    c: poise::ApplicationContext<'_, Data, anyhow::Error>,
+    #[description = "schematic, base64"] s: Option<String>,
+    #[description = "schematic, msch"] file: Option<Attachment>,
 ) -> Result<()> {
-    log(&c);
-    let Ok(schematic::Schem { schem: mut s }) = schematic::from_b64(&*s) else {
-        c.reply("no schem!").await?;
+    log(&c.into());
+    const TIMEOUT: Duration = Duration::from_secs(60 * 5);
+    // the modal has to open within 3 seconds, so files are downloaded after it, and it starts out empty for them
+    let decoded = match s.as_deref().map(schematic::from_b64) {
+        Some(Ok(s)) => Some(s),
+        None if file.is_some() => None,
+        _ => {
+            poise::say_reply(c.into(), "no schem!").await?;
+            return Ok(());
+        }
+    };
+    let keep = decoded.is_none();
+    let Some(meta) =
+        poise::execute_modal(c, decoded.as_ref().map(|x| Meta::of(x)), Some(TIMEOUT)).await?
+    else {
         return Ok(());
     };
-    s.tags.insert("name".to_string(), name);
-    let mut o = DataWrite::default();
-    s.serialize(&mut o)?;
-    poise::send_reply(
-        c,
-        CreateReply::default().attachment(CreateAttachment::bytes(o.consume(), "out.msch")),
+    let Some(schematic::Schem { schem: mut s }) = (match decoded {
+        Some(x) => Some(x),
+        None => schematic::from_either(None, file).await?,
+    }) else {
+        poise::say_reply(c.into(), "no schem!").await?;
+        return Ok(());
+    };
+    if let Err(e) = meta.apply(&mut s, keep) {
+        poise::say_reply(c.into(), e).await?;
+        return Ok(());
+    }
+    let (author, avatar) = (c.author().name.clone(), c.author().face());
+    let preview = schematic::reply(
+        vec![schematic::Schem { schem: s.clone() }],
+        &author,
+        &avatar,
+        schematic::Style::default(),
     )
-    .await?;
-    Ok(())
+    .await?
+    .components(meta_buttons());
+    let r = poise::send_reply(c.into(), preview).await?;
+    let id = r.message().await?.id;
+    loop {
+        let Some(i) = ComponentInteractionCollector::new(c.serenity_context())
+            .message_id(id)
+            .author_id(c.author().id)
+            .timeout(TIMEOUT)
+            .next()
+            .await
+        else {
+            r.edit(c.into(), CreateReply::default().components(vec![]))
+                .await?;
+            return Ok(());
+        };
+        match &*i.data.custom_id {
+            "edit" => {
+                let Some(meta) = poise::execute_modal_on_component_interaction(
+                    c.serenity_context(),
+                    i,
+                    Some(Meta::of(&s)),
+                    Some(TIMEOUT),
+                )
+                .await?
+                else {
+                    continue;
+                };
+                let mut next = s.clone();
+                let e = match meta.apply(&mut next, false) {
+                    Ok(()) => {
+                        s = next;
+                        schematic::e(&author, &avatar, &schematic::Schem { schem: s.clone() })
+                            .title(emoji::mindustry::to_discord(&strip_colors(
+                                s.tags.get("name").unwrap(),
+                            )))
+                    }
+                    Err(e) => {
+                        poise::say_reply(c.into(), e).await?;
+                        continue;
+                    }
+                };
+                r.edit(
+                    c.into(),
+                    CreateReply::default()
+                        .embed(e.attachment("image.png"))
+                        .components(meta_buttons()),
+                )
+                .await?;
+            }
+            "done" => {
+                i.create_response(
+                    c.serenity_context(),
+                    CreateInteractionResponse::UpdateMessage(
+                        CreateInteractionResponseMessage::new().components(vec![]),
+                    ),
+                )
+                .await?;
+                poise::send_reply(
+                    c.into(),
+                    schematic::output(schematic::Schem { schem: s }, &author, &avatar).await?,
+                )
+                .await?;
+                return Ok(());
+            }
+            _ => (),
+        }
+    }
 }
 
 #[poise::command(
@@ -1080,24 +1443,31 @@ async fn rename(
     install_context = "User|Guild",
     interaction_context = "Guild|PrivateChannel"
 )]
-/// Renders schematic inside a message.
+/// Renders the schematics inside a message, in the style of your last `/render` (within the hour).
 pub async fn render_message(c: Context<'_>, m: Message) -> Result<()> {
     log(&c);
+    let style = STYLES.get(c.author().id);
     poise::send_reply(
         c,
-        match schematic::from((&m.content, &m.attachments)).await {
-            Ok(Some(s)) => {
-                schematic::reply(
+        match schematic::all((&m.content, &m.attachments)).await {
+            Ok(s) if !s.is_empty() => {
+                let r = schematic::reply(
                     s,
                     &m.author_nick(c)
                         .await
                         .unwrap_or_else(|| m.author.name.clone()),
                     &m.author.face(),
+                    style.unwrap_or_default(),
                 )
-                .await?
+                .await?;
+                // so it's clear where the style came from
+                match style {
+                    Some(_) => r.content("-# in the style of your last `/render`"),
+                    None => r,
+                }
             }
             Err(e) => CreateReply::default().content(format!("schematic error {e}")),
-            Ok(None) => CreateReply::default()
+            Ok(_) => CreateReply::default()
                 .content("no schem found")
                 .ephemeral(true),
         },
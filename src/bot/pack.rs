@@ -0,0 +1,219 @@
+use super::schematic::{self, Schem};
+use super::{Context, Result};
+use mindus::Schematic;
+use mindus::block::logic::ProcessorState;
+use mindus::block::{self, Block, Rotation};
+use mindus::data::dynamic::DynData;
+use poise::{ChoiceParameter, CodeBlock, KeyValueArgs, serenity_prelude::*};
+
+#[derive(ChoiceParameter, Copy, Clone, Default)]
+pub enum Processor {
+    #[default]
+    #[name = "micro"]
+    Micro,
+    #[name = "logic"]
+    Logic,
+    #[name = "hyper"]
+    Hyper,
+}
+
+#[derive(ChoiceParameter, Copy, Clone)]
+pub enum Display {
+    /// 3×3
+    #[name = "small"]
+    Small,
+    /// 6×6
+    #[name = "large"]
+    Large,
+}
+
+#[derive(ChoiceParameter, Copy, Clone)]
+pub enum Memory {
+    #[name = "cell"]
+    Cell,
+    #[name = "bank"]
+    Bank,
+}
+
+impl Processor {
+    fn block(self) -> &'static Block {
+        match self {
+            Self::Micro => &block::MICRO_PROCESSOR,
+            Self::Logic => &block::LOGIC_PROCESSOR,
+            Self::Hyper => &block::HYPER_PROCESSOR,
+        }
+    }
+
+    fn icon(self) -> &'static str {
+        use crate::emoji::to_mindustry::named::*;
+        match self {
+            Self::Micro => MICRO_PROCESSOR,
+            Self::Logic => LOGIC_PROCESSOR,
+            Self::Hyper => HYPER_PROCESSOR,
+        }
+    }
+}
+
+impl Display {
+    fn block(self) -> &'static Block {
+        match self {
+            Self::Small => &block::LOGIC_DISPLAY,
+            Self::Large => &block::LARGE_LOGIC_DISPLAY,
+        }
+    }
+}
+
+impl Memory {
+    fn block(self) -> &'static Block {
+        match self {
+            Self::Cell => &block::MEMORY_CELL,
+            Self::Bank => &block::MEMORY_BANK,
+        }
+    }
+}
+
+/// For `}pack` key value args.
+fn choice<T: ChoiceParameter>(kv: &KeyValueArgs, k: &str) -> Option<T> {
+    T::from_name(kv.get(k)?)
+}
+
+/// Builds a schematic with the processor in the bottom left, the display to its right and the memory above it, all linked.
+pub fn pack(
+    code: &str,
+    processor: Processor,
+    display: Option<Display>,
+    memory: Option<Memory>,
+) -> anyhow::Result<Schematic> {
+    let size = |b: &Block| b.get_size() as usize;
+    // center of a block whose bottom left is at x, y
+    let center = |b: &Block, x: usize, y: usize| (x + (size(b) - 1) / 2, y + (size(b) - 1) / 2);
+    let p = processor.block();
+    let (d, m) = (display.map(Display::block), memory.map(Memory::block));
+    let width = size(p) + d.map_or(0, size);
+    let height = (size(p) + m.map_or(0, size)).max(d.map_or(0, size));
+    let mut s = Schematic::new(width, height);
+    let (px, py) = center(p, 0, 0);
+    s.set(px, py, p, DynData::Empty, Rotation::Up)?;
+    let mut links = vec![];
+    if let Some(d) = d {
+        let (x, y) = center(d, size(p), 0);
+        s.set(x, y, d, DynData::Empty, Rotation::Up)?;
+        links.push(("display1", x, y));
+    }
+    if let Some(m) = m {
+        let (x, y) = center(m, 0, size(p));
+        s.set(x, y, m, DynData::Empty, Rotation::Up)?;
+        links.push((
+            match memory {
+                Some(Memory::Bank) => "bank1",
+                _ => "cell1",
+            },
+            x,
+            y,
+        ));
+    }
+    let state = s
+        .get_mut(px, py)?
+        .unwrap()
+        .get_state_mut()
+        .unwrap()
+        .downcast_mut::<ProcessorState>()
+        .unwrap();
+    state.set_code(code)?;
+    for (name, x, y) in links {
+        state.create_link(
+            name.to_string(),
+            (x as i32 - px as i32) as i16,
+            (y as i32 - py as i32) as i16,
+        )?;
+    }
+    let icon = processor.icon();
+    s.tags
+        .insert("labels".to_string(), format!(r#"["{icon}"]"#));
+    s.tags.insert(
+        "name".to_string(),
+        format!(
+            "{icon} {}",
+            code.lines()
+                .next()
+                .unwrap_or("")
+                .chars()
+                .take(32)
+                .collect::<String>()
+        ),
+    );
+    Ok(s)
+}
+
+async fn reply(
+    c: Context<'_>,
+    code: String,
+    processor: Option<Processor>,
+    display: Option<Display>,
+    memory: Option<Memory>,
+) -> Result<()> {
+    // against what gets linked, so a `cell1` without memory is caught here and not in game
+    if let Err(e) = super::logic::linked(
+        &code,
+        display.as_slice(),
+        match memory {
+            Some(Memory::Cell) => (1, 0),
+            Some(Memory::Bank) => (0, 1),
+            None => (0, 0),
+        },
+        1,
+    ) {
+        c.send(
+            poise::CreateReply::default()
+                .allowed_mentions(CreateAllowedMentions::default().empty_users().empty_roles())
+                .content(format!("```ansi\n{e}\n```")),
+        )
+        .await?;
+        return Ok(());
+    }
+    let s = pack(&code, processor.unwrap_or_default(), display, memory)?;
+    poise::send_reply(
+        c,
+        schematic::output(Schem { schem: s }, &c.author().name, &c.author().face()).await?,
+    )
+    .await?;
+    Ok(())
+}
+
+#[poise::command(prefix_command, track_edits, rename = "pack")]
+/// Pack MLOG into a processor schematic.
+///
+/// Optionally pass `processor=micro|logic|hyper`, `display=small|large` and `memory=cell|bank`.
+pub async fn pack_block(
+    c: Context<'_>,
+    #[description = "processor, display and memory"] kv: KeyValueArgs,
+    #[description = "Script"] block: CodeBlock,
+) -> Result<()> {
+    super::log(&c);
+    reply(
+        c,
+        block.code,
+        choice(&kv, "processor"),
+        choice(&kv, "display"),
+        choice(&kv, "memory"),
+    )
+    .await
+}
+
+#[poise::command(slash_command, install_context = "Guild|User")]
+/// Pack MLOG from a file into a processor schematic.
+pub async fn pack_file(
+    c: Context<'_>,
+    #[description = "logic, txt"] mlog: Attachment,
+    #[description = "processor, defaults to micro"] processor: Option<Processor>,
+    #[description = "display to link as display1"] display: Option<Display>,
+    #[description = "memory to link as cell1 / bank1"] memory: Option<Memory>,
+) -> Result<()> {
+    super::log(&c);
+    c.defer().await?;
+    let Ok(code) = String::from_utf8(mlog.download().await?) else {
+        c.say("this is not a mlog file!").await?;
+        return Ok(());
+    };
+    reply(c, code, processor, display, memory).await
+}
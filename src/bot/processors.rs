@@ -0,0 +1,130 @@
+use super::schematic::{self, Schem};
+use super::{Context, SUCCESS, strip_colors};
+use crate::emoji;
+use anyhow::Result;
+use mindus::Schematic;
+use mindus::block::logic::ProcessorState;
+use mindus::data::GridPos;
+use poise::{CreateReply, serenity_prelude::*};
+use std::fmt::Write;
+
+pub struct Processor {
+    pub at: GridPos,
+    pub kind: &'static str,
+    pub code: String,
+    /// (name, absolute x, absolute y)
+    pub links: Vec<(String, i32, i32)>,
+}
+
+pub fn find(s: &Schematic) -> Vec<Processor> {
+    s.block_iter()
+        .filter_map(|(at @ GridPos(x, y), p)| {
+            let ProcessorState { code, links, .. } =
+                p.get_state()?.downcast_ref::<ProcessorState>()?;
+            Some(Processor {
+                at,
+                kind: p.block.name(),
+                code: code.clone(),
+                links: links
+                    .iter()
+                    .map(|l| (l.name.clone(), x as i32 + l.x as i32, y as i32 + l.y as i32))
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+#[poise::command(
+    slash_command,
+    install_context = "User|Guild",
+    interaction_context = "Guild|BotDm|PrivateChannel"
+)]
+/// Extract the code of every processor in a schematic.
+pub async fn processors(
+    c: Context<'_>,
+    #[description = "schematic, base64"] s: Option<String>,
+    #[description = "schematic, msch"] file: Option<Attachment>,
+    #[description = "run this processor (by number) with eval"] run: Option<usize>,
+    #[description = "number of iterations (1–50)"] iterations: Option<u8>,
+) -> Result<()> {
+    use emoji::named::*;
+    super::log(&c);
+    c.defer().await?;
+    let Some(Schem { schem: s }) = schematic::from_either(s, file).await? else {
+        c.reply("no schem!").await?;
+        return Ok(());
+    };
+    let ps = find(&s);
+    if ps.is_empty() {
+        c.reply(format!("{CANCEL} no processors")).await?;
+        return Ok(());
+    }
+    let mut list = String::new();
+    let mut r = CreateReply::default();
+    for (n, Processor { at: GridPos(x, y), kind, code, links }) in (1..).zip(&ps) {
+        let line = format!(
+            "{n}. `{kind}` at ({x}, {y}): {} lines, links: {}\n",
+            code.lines().count(),
+            if links.is_empty() {
+                "none".to_string()
+            } else {
+                links
+                    .iter()
+                    .map(|(name, x, y)| format!("`{name}` ({x}, {y})"))
+                    .intersperse(", ".to_string())
+                    .collect::<String>()
+            }
+        );
+        if list.len() + line.len() > 3900 {
+            write!(list, "…and {} more", ps.len() - n + 1).unwrap();
+            break;
+        }
+        list.push_str(&line);
+        // ten attachments a message
+        if n <= 10 {
+            r = r.attachment(CreateAttachment::bytes(
+                code.as_bytes(),
+                format!("{n}_{kind}_{x}_{y}.mlog"),
+            ));
+        }
+    }
+    let name = emoji::mindustry::to_discord(&strip_colors(s.tags.get("name").unwrap()));
+    let mut e = CreateEmbed::new()
+        .title(name)
+        .description(list)
+        .color(SUCCESS);
+    if ps.len() > 10 {
+        e = e.footer(CreateEmbedFooter::new(format!(
+            "only the code of the first 10 processors is attached; {} left out",
+            ps.len() - 10
+        )));
+    }
+    poise::send_reply(c, r.embed(e)).await?;
+    if let Some(n) = run {
+        let Some(p) = n.checked_sub(1).and_then(|n| ps.get(n)) else {
+            c.say(format!("{CANCEL} there is no processor #{n}")).await?;
+            return Ok(());
+        };
+        match super::logic::exec(
+            p.code.clone(),
+            iterations.map_or(1, |x| x.clamp(1, 50)) as _,
+            Default::default(),
+        )
+        .await
+        {
+            Err(super::logic::Err::Other(x)) => return Err(x),
+            Err(super::logic::Err::Lemu(x)) => {
+                c.send(
+                    CreateReply::default()
+                        .allowed_mentions(
+                            CreateAllowedMentions::default().empty_users().empty_roles(),
+                        )
+                        .content(format!("```ansi\n{x}\n```")),
+                )
+                .await?;
+            }
+            Ok(x) => drop(c.send(x).await?),
+        }
+    }
+    Ok(())
+}
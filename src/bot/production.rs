@@ -0,0 +1,215 @@
+// per block power and output rates, at full efficiency. mindus knows about costs, but not about these,
+// so this only covers serpulo blocks whose rates are fixed; the embed says as much.
+use crate::emoji;
+use crate::emoji::to_mindustry::named::*;
+use mindus::Schematic;
+use std::fmt::Write;
+
+/// power units / second. negative for consumers.
+static POWER_RATES: phf::Map<&str, f32> = phf::phf_map! {
+    "combustion-generator" => 60.0,
+    "steam-generator" => 330.0,
+    "differential-generator" => 1080.0,
+    "rtg-generator" => 270.0,
+    "solar-panel" => 6.0,
+    "large-solar-panel" => 78.0,
+    "thorium-reactor" => 900.0,
+    // 7800 generated, 1500 to keep it running
+    "impact-reactor" => 6300.0,
+    "silicon-smelter" => -30.0,
+    "kiln" => -36.0,
+    "plastanium-compressor" => -180.0,
+    "phase-weaver" => -300.0,
+    "surge-smelter" => -240.0,
+    "cryofluid-mixer" => -60.0,
+    "pyratite-mixer" => -12.0,
+    "blast-mixer" => -24.0,
+    "melter" => -60.0,
+    "separator" => -66.0,
+    "disassembler" => -240.0,
+    "pulverizer" => -30.0,
+    "spore-press" => -42.0,
+    "multi-press" => -108.0,
+    "silicon-crucible" => -240.0,
+    "coal-centrifuge" => -42.0,
+    "cultivator" => -80.0,
+    "laser-drill" => -66.0,
+    "blast-drill" => -180.0,
+    "water-extractor" => -90.0,
+    "oil-extractor" => -180.0,
+    "rotary-pump" => -18.0,
+    "impulse-pump" => -78.0,
+    "mender" => -18.0,
+    "overdrive-projector" => -210.0,
+    "overdrive-dome" => -600.0,
+    "force-projector" => -240.0,
+    "ground-factory" => -72.0,
+    "air-factory" => -72.0,
+    "naval-factory" => -72.0,
+    "additive-reconstructor" => -180.0,
+    "multiplicative-reconstructor" => -360.0,
+    "exponential-reconstructor" => -780.0,
+    "tetrative-reconstructor" => -1500.0,
+    "mass-driver" => -105.0,
+    "launch-pad" => -240.0,
+    "advanced-launch-pad" => -240.0,
+};
+
+/// power blocks whose rate depends on their surroundings or on what they are doing, so can't be counted.
+static VARYING: phf::Set<&str> = phf::phf_set! {
+    // depends on the heat tiles underneath
+    "thermal-generator",
+    // only draw power while shooting
+    "arc",
+    "lancer",
+    "segment",
+    "meltdown",
+    // store, not produce
+    "battery",
+    "battery-large",
+};
+
+/// (output, units / second)
+static OUTPUTS: phf::Map<&str, (&str, f32)> = phf::phf_map! {
+    "silicon-smelter" => (SILICON, 1.5),
+    "graphite-press" => (GRAPHITE, 0.67),
+    "kiln" => (METAGLASS, 2.0),
+    "plastanium-compressor" => (PLASTANIUM, 1.0),
+    "phase-weaver" => (PHASEFABRIC, 0.5),
+    "surge-smelter" => (SURGEALLOY, 0.8),
+    "cryofluid-mixer" => (CRYOFLUID, 12.0),
+    "pyratite-mixer" => (PYRATITE, 0.75),
+    "blast-mixer" => (BLASTCOMPOUND, 0.75),
+    "melter" => (SLAG, 12.0),
+    "pulverizer" => (SAND, 1.5),
+    "spore-press" => (OIL, 18.0),
+    "multi-press" => (GRAPHITE, 4.0),
+    "silicon-crucible" => (SILICON, 5.33),
+    "coal-centrifuge" => (COAL, 2.0),
+    "cultivator" => (SPOREPOD, 0.6),
+    "water-extractor" => (WATER, 6.6),
+    "oil-extractor" => (OIL, 15.0),
+};
+
+/// `(generation, consumption, uncounted)`, rates positive, if anything in the schematic touches power.
+pub fn power(s: &Schematic) -> Option<(f32, f32, usize)> {
+    let mut any = false;
+    let (mut gen_, mut use_, mut skipped) = (0.0, 0.0, 0);
+    for (_, p) in s.block_iter() {
+        if VARYING.contains(p.block.name()) {
+            any = true;
+            skipped += 1;
+        } else if let Some(&n) = POWER_RATES.get(p.block.name()) {
+            any = true;
+            if n > 0.0 {
+                gen_ += n;
+            } else {
+                use_ -= n;
+            }
+        }
+    }
+    any.then_some((gen_, use_, skipped))
+}
+
+pub fn power_field(s: &Schematic) -> Option<String> {
+    power(s).map(|(g, u, skipped)| {
+        let net = g - u;
+        let mut s = format!(
+            "{} +{g:.0}/s −{u:.0}/s = **{}{net:.0}/s**\n-# fixed rate serpulo blocks only",
+            emoji::mindustry::to_discord(POWER),
+            if net >= 0.0 { "+" } else { "" }
+        );
+        if skipped != 0 {
+            write!(s, "; {skipped} varying blocks not counted").unwrap();
+        }
+        s
+    })
+}
+
+pub fn produces(s: &Schematic) -> Option<String> {
+    let mut out: Vec<(&str, f32)> = vec![];
+    for (_, p) in s.block_iter() {
+        if let Some(&(what, n)) = OUTPUTS.get(p.block.name()) {
+            match out.iter_mut().find(|(x, _)| *x == what) {
+                Some((_, x)) => *x += n,
+                None => out.push((what, n)),
+            }
+        }
+    }
+    if out.is_empty() {
+        return None;
+    }
+    out.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let mut s = String::new();
+    for (what, n) in out {
+        write!(s, "{} {n:.1}/s ", emoji::mindustry::to_discord(what)).unwrap();
+    }
+    s.push_str("\n-# serpulo crafters only");
+    Some(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mindus::block::{self, Block, Rotation};
+    use mindus::data::dynamic::DynData;
+
+    /// lays `blocks` out along a row, well apart.
+    fn row(blocks: &[&Block]) -> Schematic {
+        let mut s = Schematic::new(blocks.len() * 4 + 4, 4);
+        for (i, b) in blocks.iter().enumerate() {
+            s.set(i * 4 + 1, 1, b, DynData::Empty, Rotation::Up)
+                .unwrap();
+        }
+        s
+    }
+
+    #[test]
+    fn sums_generation_and_use() {
+        let s = row(&[
+            &block::COMBUSTION_GENERATOR,
+            &block::COMBUSTION_GENERATOR,
+            &block::SILICON_SMELTER,
+        ]);
+        assert_eq!(power(&s), Some((120.0, 30.0, 0)));
+        assert!(power_field(&s).unwrap().contains("**+90/s**"));
+    }
+
+    #[test]
+    fn varying_blocks_are_skipped() {
+        let s = row(&[&block::BATTERY, &block::SOLAR_PANEL]);
+        assert_eq!(power(&s), Some((6.0, 0.0, 1)));
+        assert!(power_field(&s).unwrap().contains("1 varying blocks"));
+    }
+
+    #[test]
+    fn nothing_without_power() {
+        let s = row(&[&block::CONVEYOR]);
+        assert_eq!(power(&s), None);
+        assert_eq!(produces(&s), None);
+    }
+
+    #[test]
+    fn tables_agree() {
+        for k in VARYING.iter() {
+            assert!(
+                !POWER_RATES.contains_key(k),
+                "{k} is both fixed and varying"
+            );
+        }
+    }
+
+    #[test]
+    fn outputs_are_grouped() {
+        let s = row(&[
+            &block::GRAPHITE_PRESS,
+            &block::MULTI_PRESS,
+            &block::SILICON_SMELTER,
+        ]);
+        let p = produces(&s).unwrap();
+        // graphite first, both presses together
+        let graphite = emoji::mindustry::to_discord(GRAPHITE);
+        assert!(p.starts_with(&format!("{graphite} 4.7/s ")));
+        assert_eq!(p.matches(graphite).count(), 1);
+    }
+}
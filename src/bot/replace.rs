@@ -0,0 +1,114 @@
+use super::schematic::{self, Schem};
+use super::{AD, Context};
+use crate::emoji;
+use anyhow::Result;
+use mindus::Schematic;
+use mindus::block::{BLOCK_REGISTRY, Block};
+use mindus::data::GridPos;
+use mindus::data::dynamic::DynData;
+use poise::{CreateReply, serenity_prelude::*};
+
+async fn block(_: Context<'_>, partial: &str) -> impl Iterator<Item = String> {
+    let mut v = BLOCK_REGISTRY
+        .keys()
+        .filter(|x| x.contains(partial))
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>();
+    v.sort_by_key(|x| x.len());
+    v.into_iter().take(25)
+}
+
+/// Swaps every `from` for a `to`, keeping rotation, and config where `to` accepts it.
+/// Returns how many blocks were replaced.
+pub fn replace(s: &mut Schematic, from: &'static Block, to: &'static Block) -> usize {
+    let found = s
+        .block_iter()
+        .filter(|(_, p)| p.block == from)
+        .map(|(pos, p)| {
+            (
+                pos,
+                p.rot,
+                p.get_state()
+                    .and_then(|st| from.serialize_state(st).ok())
+                    .unwrap_or(DynData::Empty),
+            )
+        })
+        .collect::<Vec<_>>();
+    for &(GridPos(x, y), rot, ref data) in &found {
+        s.take(x, y).unwrap();
+        if s.set(x, y, to, data.clone(), rot).is_err() {
+            s.set(x, y, to, DynData::Empty, rot).unwrap();
+        }
+    }
+    found.len()
+}
+
+#[poise::command(
+    slash_command,
+    install_context = "User|Guild",
+    interaction_context = "Guild|BotDm|PrivateChannel",
+    rename = "replace"
+)]
+/// Replace every block of one type with another of the same size.
+pub async fn swap(
+    c: Context<'_>,
+    #[description = "block to replace, eg conveyor"]
+    #[autocomplete = "block"]
+    from: String,
+    #[description = "replacement, eg titanium-conveyor"]
+    #[autocomplete = "block"]
+    to: String,
+    #[description = "schematic, base64"] s: Option<String>,
+    #[description = "schematic, msch"] file: Option<Attachment>,
+) -> Result<()> {
+    use emoji::named::*;
+    super::log(&c);
+    c.defer().await?;
+    let (Some(&from), Some(&to)) = (BLOCK_REGISTRY.get(&*from), BLOCK_REGISTRY.get(&*to)) else {
+        c.reply(format!("{CANCEL} no such block")).await?;
+        return Ok(());
+    };
+    if from.get_size() != to.get_size() {
+        c.reply(format!(
+            "{CANCEL} `{}` is {}×{}, but `{}` is {}×{}",
+            from.name(),
+            from.get_size(),
+            from.get_size(),
+            to.name(),
+            to.get_size(),
+            to.get_size()
+        ))
+        .await?;
+        return Ok(());
+    }
+    let Some(Schem { schem: mut s }) = schematic::from_either(s, file).await? else {
+        c.reply("no schem!").await?;
+        return Ok(());
+    };
+    let before = schematic::cost(&s);
+    let n = replace(&mut s, from, to);
+    if n == 0 {
+        c.reply(format!("{CANCEL} no `{}` in this schematic", from.name()))
+            .await?;
+        return Ok(());
+    }
+    let after = schematic::cost(&s);
+    let r: CreateReply =
+        schematic::output(Schem { schem: s }, &c.author().name, &c.author().face()).await?;
+    poise::send_reply(
+        c,
+        r.embed(
+            CreateEmbed::new()
+                .description(format!(
+                    "{RIGHT} replaced {n} `{}` with `{}`",
+                    from.name(),
+                    to.name()
+                ))
+                .field("before", before, true)
+                .field("after", after, true)
+                .color(AD),
+        ),
+    )
+    .await?;
+    Ok(())
+}
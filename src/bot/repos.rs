@@ -43,6 +43,17 @@ impl Repo {
             }))
     }
 
+    /// Pings for every admin.
+    pub fn mention_admins(&self) -> String {
+        self.admins
+            .iter()
+            .map(|x| match x {
+                Person::Role(x) => format!("<@&{x}>"),
+                Person::User(x) => format!("<@{x}>"),
+            })
+            .collect()
+    }
+
     pub async fn own(&self) -> tokio::sync::MutexGuard<Ownership> {
         self.ownership.lock().await
     }
@@ -323,10 +334,10 @@ EVICT => [
 
 macro_rules! chief {
     ($c:ident) => {{
-        let repo = repos::SPECIAL
-            .get(&$c.channel_id().get())
-            .ok_or(anyhow::anyhow!("not repo"))?
-            .repo;
+        let Some(repo) = repos::SPECIAL.get(&$c.channel_id().get()).map(|x| x.repo) else {
+            poise::say_reply($c, "this is not a repo channel.").await?;
+            return Ok(());
+        };
         if repo.chief != $c.author().id.get() && $c.author().id.get() != OWNER {
             poise::send_reply(
                 $c,
@@ -3,7 +3,7 @@ use crate::emoji;
 use anyhow::Result;
 use base64::Engine;
 use logos::Logos;
-use mindus::data::DataRead;
+use mindus::data::{DataRead, GridPos};
 use mindus::data::schematic::R64Error;
 use mindus::*;
 use poise::{CreateReply, serenity_prelude::*};
@@ -32,8 +32,19 @@ impl Deref for Schem {
     }
 }
 
+/// discord allows at most ten embeds (and we use one attachment each) per message.
+const MAX: usize = 10;
+
 pub async fn from_attachments(attchments: &[Attachment]) -> Result<Option<Schem>> {
+    Ok(all_attachments(attchments).await?.into_iter().next())
+}
+
+pub async fn all_attachments(attchments: &[Attachment]) -> Result<Vec<Schem>> {
+    let mut v = vec![];
     for a in attchments {
+        if v.len() == MAX {
+            break;
+        }
         if a.filename.ends_with("msch") {
             let sd = a.download().await?;
             let mut s = DataRead::new(&sd);
@@ -41,27 +52,82 @@ pub async fn from_attachments(attchments: &[Attachment]) -> Result<Option<Schem>
                 println!("failed to read {}", a.filename);
                 continue;
             };
-            return Ok(Some(Schem { schem: s }));
+            v.push(Schem { schem: s });
         // discord uploads base64 as a file when its too long
         } else if a.filename == "message.txt" {
             let Ok(s) = String::from_utf8(a.download().await?) else {
                 continue;
             };
-            let schem = Schematic::deserialize_base64(&s)?;
-            return Ok(Some(Schem { schem }));
+            let Ok(schem) = Schematic::deserialize_base64(&s) else {
+                println!("failed to read {}", a.filename);
+                continue;
+            };
+            v.push(Schem { schem });
+        } else if super::archive::is(a) {
+            for (_, b) in super::archive::download(a).await? {
+                if v.len() == MAX {
+                    break;
+                }
+                if let Ok(schem) = Schematic::deserialize(&mut DataRead::new(&b)) {
+                    v.push(Schem { schem });
+                }
+            }
         }
     }
-    Ok(None)
+    Ok(v)
 }
 
-pub async fn reply(v: Schem, author: &str, avatar: &str) -> Result<CreateReply> {
-    let name = emoji::mindustry::to_discord(&strip_colors(v.tags.get("name").unwrap()));
-    let vclone = v.clone();
-    let p = tokio::task::spawn_blocking(move || to_png(&vclone)).await?;
-    println!("rend {name}");
-    Ok(CreateReply::default()
-        .attachment(CreateAttachment::bytes(p, "image.png"))
-        .embed(e(author, avatar, &v).title(name)))
+pub async fn reply(
+    v: Vec<Schem>,
+    author: &str,
+    avatar: &str,
+    style: Style,
+) -> Result<CreateReply> {
+    let mut r = CreateReply::default();
+    for (i, v) in v.into_iter().enumerate() {
+        let name = emoji::mindustry::to_discord(&strip_colors(v.tags.get("name").unwrap()));
+        let vclone = v.clone();
+        let p = match tokio::task::spawn_blocking(move || to_png_with(&vclone, &style)).await {
+            Ok(p) => p,
+            // the guild and command get filled in by on_error
+            Err(e) => return Err(crashed(&v, e, "?".to_string(), "?").into()),
+        };
+        println!("rend {name}");
+        r = r
+            .attachment(CreateAttachment::bytes(p, image(i)))
+            .embed(e(author, avatar, &v).title(name).attachment(image(i)));
+    }
+    Ok(r)
+}
+
+/// The schematic's name, made safe to name a file with.
+pub fn file_name(v: &Schematic) -> String {
+    strip_colors(v.tags.get("name").unwrap()).replace(['/', '\\', ' '], "_")
+}
+
+/// Like [`reply`], but also hands back the schematic, as a `.msch` and as base64.
+pub async fn output(v: Schem, author: &str, avatar: &str) -> Result<CreateReply> {
+    let mut o = mindus::data::DataWrite::default();
+    v.serialize(&mut o)?;
+    let b64 = v.serialize_base64()?;
+    let file = format!("{}.msch", file_name(&v));
+    let mut r = reply(vec![v], author, avatar, Style::default())
+        .await?
+        .attachment(CreateAttachment::bytes(o.consume(), file));
+    // leave room for the fences
+    r = if b64.len() < 1990 {
+        r.content(format!("```\n{b64}\n```"))
+    } else {
+        r.attachment(CreateAttachment::bytes(b64, "message.txt"))
+    };
+    Ok(r)
+}
+
+fn image(i: usize) -> String {
+    match i {
+        0 => "image.png".to_string(),
+        n => format!("image{n}.png"),
+    }
 }
 
 fn tags(v: &Schem) -> Option<String> {
@@ -74,7 +140,7 @@ fn tags(v: &Schem) -> Option<String> {
     })
 }
 
-fn cost(v: &Schem) -> String {
+pub fn cost(v: &Schematic) -> String {
     let mut s = String::new();
     for (i, n) in v.compute_total_cost().0.iter() {
         if n == 0 {
@@ -82,10 +148,14 @@ fn cost(v: &Schem) -> String {
         }
         write!(s, "{} {n} ", emoji::mindustry::item(i)).unwrap();
     }
+    // discord rejects empty fields
+    if s.is_empty() {
+        s.push_str("none");
+    }
     s
 }
 
-fn e(author: &str, avatar: &str, v: &Schem) -> CreateEmbed {
+pub fn e(author: &str, avatar: &str, v: &Schem) -> CreateEmbed {
     let mut e = CreateEmbed::new()
         .attachment("image.png")
         .author(CreateEmbedAuthor::new(author).icon_url(avatar));
@@ -104,32 +174,95 @@ fn e(author: &str, avatar: &str, v: &Schem) -> CreateEmbed {
     } else {
         format!("{}×{}={}", v.height, v.width, v.width * v.height)
     };
-    e.field("req", cost(&v), true)
-        .footer(CreateEmbedFooter::new(f))
-        .color(SUCCESS)
+    e = e.field("req", cost(&v), true);
+    if let Some(p) = super::production::power_field(v) {
+        e = e.field("power", p, true);
+    }
+    if let Some(p) = super::production::produces(v) {
+        e = e.field("produces", p, true);
+    }
+    e.footer(CreateEmbedFooter::new(f)).color(SUCCESS)
 }
 
 pub async fn send(
     m: Msg,
     c: &serenity::client::Context,
-    v: Schem,
-) -> Result<(poise::serenity_prelude::Message, std::string::String, Schem)> {
-    let name = emoji::mindustry::to_discord(&strip_colors(v.tags.get("name").unwrap()));
-    let vclone = v.clone();
-    println!("rend {name} (shard# {})", c.shard_id.0);
-    let p = tokio::task::spawn_blocking(move || to_png(&vclone)).await?;
-    let msg = CreateMessage::new()
-        .add_file(CreateAttachment::bytes(p, "image.png"))
-        .embed(e(&m.author, &m.avatar, &v).title(name.clone()));
+    v: Vec<Schem>,
+) -> Result<(poise::serenity_prelude::Message, std::string::String, Vec<Schem>)> {
+    let name = emoji::mindustry::to_discord(&strip_colors(v[0].tags.get("name").unwrap()));
+    let mut msg = CreateMessage::new();
+    for (i, v) in v.iter().enumerate() {
+        let vclone = v.clone();
+        println!("rend {} (shard# {})", v.tags.get("name").unwrap(), c.shard_id.0);
+        let p = match tokio::task::spawn_blocking(move || to_png(&vclone)).await {
+            Ok(p) => p,
+            Err(e) => {
+                let guild = match m.guild {
+                    0 => format!("dms with {}", m.author),
+                    g => g.to_string(),
+                };
+                let f = crashed(v, e, guild, "schematic message input");
+                m.channel
+                    .say(
+                        c,
+                        format!(
+                            "{} there was an error while rendering this schematic.\nit has been logged as {f}.",
+                            emoji::named::CANCEL
+                        ),
+                    )
+                    .await?;
+                return Err(f.into());
+            }
+        };
+        let n = emoji::mindustry::to_discord(&strip_colors(v.tags.get("name").unwrap()));
+        msg = msg
+            .add_file(CreateAttachment::bytes(p, image(i)))
+            .embed(e(&m.author, &m.avatar, v).title(n).attachment(image(i)));
+    }
     let h = m.channel.send_message(c, msg).await?;
     Ok((h, name, v))
 }
 
+/// Applies a repo channel's labels to `v`.
+pub fn label(v: &mut Schem, labels: &Option<super::Type>) {
+    let Some(super::Type::Basic(x)) = labels else {
+        return;
+    };
+    use emoji::to_mindustry::named::*;
+    let x = if let Some(i) = x.iter().position(|x| x == &repos::L) {
+        let mut x = x.to_vec();
+        if v.block_iter().any(|x| {
+            x.1.block == &mindus::block::ADVANCED_LAUNCH_PAD
+                || x.1.block == &mindus::block::LAUNCH_PAD
+        }) {
+            x[i] = ADVANCED_LAUNCH_PAD;
+        } else {
+            x.remove(i);
+        }
+        super::tags(&x)
+    } else if x.contains(&"find unit factory") {
+        super::tags(&[v
+            .block_iter()
+            .find_map(|x| match x.1.block.name() {
+                "air-factory" => Some(AIR_FACTORY),
+                "ground-factory" => Some(GROUND_FACTORY),
+                "naval-factory" => Some(NAVAL_FACTORY),
+                _ => None,
+            })
+            .unwrap_or(AIR_FACTORY)])
+    } else {
+        super::tags(x)
+    };
+    v.schem.tags.insert("labels".into(), x);
+}
+
 pub async fn with(
     m: Msg,
     labels: Option<super::Type>,
-) -> Result<ControlFlow<(u64, Msg, Schem), ()>> {
-    if let Ok(Some(mut v)) = from((&m.content, &m.attachments)).await {
+) -> Result<ControlFlow<(u64, Msg, Vec<Schem>), ()>> {
+    if let Ok(mut vs) = all((&m.content, &m.attachments)).await
+        && !vs.is_empty()
+    {
         super::data::push_j(serde_json::json! {{
         "locale": m.locale,
         "name":  m.author,
@@ -138,45 +271,181 @@ pub async fn with(
         "guild":  m.guild,
         "channel": m.channel.get(),
         }});
-        if let Some(super::Type::Basic(x)) = labels {
-            use emoji::to_mindustry::named::*;
-            let x = if let Some(i) = x.iter().position(|x| x == &repos::L) {
-                let mut x = x.to_vec();
-                if v.block_iter().any(|x| {
-                    x.1.block == &mindus::block::ADVANCED_LAUNCH_PAD
-                        || x.1.block == &mindus::block::LAUNCH_PAD
-                }) {
-                    x[i] = ADVANCED_LAUNCH_PAD;
-                } else {
-                    x.remove(i);
-                }
-                super::tags(&x)
-            } else if x.contains(&"find unit factory") {
-                super::tags(&[v
-                    .block_iter()
-                    .find_map(|x| match x.1.block.name() {
-                        "air-factory" => Some(AIR_FACTORY),
-                        "ground-factory" => Some(GROUND_FACTORY),
-                        "naval-factory" => Some(NAVAL_FACTORY),
-                        _ => None,
-                    })
-                    .unwrap_or(AIR_FACTORY)])
-            } else {
-                super::tags(x)
-            };
-            v.schem.tags.insert("labels".into(), x);
-        };
-        let ha = rustc_hash::FxBuildHasher::default().hash_one(&v);
-        return Ok(ControlFlow::Break((ha, m, v)));
+        for v in &mut vs {
+            label(v, &labels);
+        }
+        // tracks the whole set, so editing any one of them re-renders
+        let ha = rustc_hash::FxBuildHasher::default().hash_one(&vs);
+        return Ok(ControlFlow::Break((ha, m, vs)));
     }
 
     Ok(ControlFlow::Continue(()))
 }
 
+/// Reports a render of `s` that panicked.
+fn crashed(
+    s: &Schematic,
+    e: tokio::task::JoinError,
+    guild: String,
+    command: &str,
+) -> super::crashes::Filed {
+    use super::crashes;
+    let mut b = mindus::data::DataWrite::default();
+    _ = s.serialize(&mut b);
+    let (error, backtrace) = crashes::panicked(e);
+    crashes::Filed(crashes::file(
+        Some((crashes::Kind::Schematic, &b.consume())),
+        &error,
+        backtrace,
+        guild,
+        command,
+    ))
+}
+
 pub fn to_png(s: &Schematic) -> Vec<u8> {
     super::png(s.render())
 }
 
+#[derive(Copy, Clone, Debug)]
+pub enum Background {
+    Transparent,
+    Solid([u8; 3]),
+}
+
+impl std::str::FromStr for Background {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().trim_start_matches('#') {
+            "transparent" | "none" => Ok(Self::Transparent),
+            x if x.len() == 6 && let Ok(n) = u32::from_str_radix(x, 16) => {
+                let [_, r, g, b] = n.to_be_bytes();
+                Ok(Self::Solid([r, g, b]))
+            }
+            _ => Err(format!("`{s}` is not `transparent` or a hex color (eg `#1e1e2e`)")),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Style {
+    /// output scale factor
+    pub scale: f32,
+    /// what to put behind empty tiles. [`None`] leaves the render as is.
+    pub background: Option<Background>,
+    /// draws a line between tiles, and a brighter, numbered one every 5.
+    pub grid: bool,
+    /// draw configs, such as sorter items
+    pub configs: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            background: None,
+            grid: false,
+            configs: true,
+        }
+    }
+}
+
+impl Style {
+    pub fn is_default(&self) -> bool {
+        self.scale == 1.0 && self.background.is_none() && !self.grid && self.configs
+    }
+}
+
+pub fn to_png_with(s: &Schematic, style: &Style) -> Vec<u8> {
+    use fimg::Image;
+    if style.is_default() {
+        return to_png(s);
+    }
+    let mut unconfigured;
+    let s = if style.configs {
+        s
+    } else {
+        unconfigured = s.clone();
+        let configured = s
+            .block_iter()
+            .filter(|(_, p)| p.get_state().is_some())
+            .map(|(pos, p)| (pos, p.block, p.rot))
+            .collect::<Vec<_>>();
+        for (GridPos(x, y), b, rot) in configured {
+            unconfigured.take(x, y).unwrap();
+            unconfigured
+                .set(x, y, b, data::dynamic::DynData::Empty, rot)
+                .unwrap();
+        }
+        &unconfigured
+    };
+    let i = s.render();
+    let (w, h) = (i.width(), i.height());
+    let scale = (w as usize / s.width.max(1)).max(1);
+    let mut occupied = vec![false; s.width * s.height];
+    for (GridPos(x, y), p) in s.block_iter() {
+        let size = p.block.get_size() as usize;
+        for tx in x.saturating_sub((size - 1) / 2)..(x + size / 2 + 1).min(s.width) {
+            for ty in y.saturating_sub((size - 1) / 2)..(y + size / 2 + 1).min(s.height) {
+                occupied[ty * s.width + tx] = true;
+            }
+        }
+    }
+    let tile = |px: usize, py: usize| {
+        let (tx, ty) = (px / scale, py / scale);
+        (tx < s.width && ty < s.height).then(|| (tx, s.height - 1 - ty))
+    };
+    let mut out = Vec::with_capacity(w as usize * h as usize * 4);
+    for (n, &[r, g, b]) in i.buffer().as_chunks::<3>().0.iter().enumerate() {
+        let (px, py) = (n % w as usize, n / w as usize);
+        let empty = tile(px, py).is_none_or(|(x, y)| !occupied[y * s.width + x]);
+        out.extend(match style.background {
+            Some(Background::Transparent) if empty => [0, 0, 0, 0],
+            Some(Background::Solid([r, g, b])) if empty => [r, g, b, 255],
+            _ => [r, g, b, 255],
+        });
+        if style.grid && (px % scale == 0 || py % scale == 0) {
+            let (tx, ty) = (px / scale, s.height.saturating_sub(py / scale));
+            let major = (px % scale == 0 && tx % 5 == 0) || (py % scale == 0 && ty % 5 == 0);
+            let [r, g, b, a] = out.last_chunk_mut::<4>().unwrap();
+            let (line, alpha) = if major { (255, 160) } else { (128, 96) };
+            for c in [r, g, b] {
+                *c = ((*c as u16 * (255 - alpha) + line * alpha) / 255) as u8;
+            }
+            *a = (*a).max(alpha as u8);
+        }
+    }
+    if style.grid {
+        // coordinates by the bright lines: x along the bottom, y up the left
+        let k = (scale / 16).max(1) as i64;
+        let (w, h, scale) = (w as i64, h as i64, scale as i64);
+        let mut put = |x: i64, y: i64, [r, g, b]: [u8; 3]| {
+            if (0..w).contains(&x) && (0..h).contains(&y) {
+                let n = (y * w + x) as usize * 4;
+                out[n..n + 4].copy_from_slice(&[r, g, b, 255]);
+            }
+        };
+        let bg = Some([0x1e; 3]);
+        for x in (0..s.width).step_by(5) {
+            let at = (x as i64 * scale + k, h - 6 * k);
+            super::font::text(&x.to_string(), at, k, bg, &mut put);
+        }
+        for y in (5..s.height).step_by(5) {
+            let at = (k, (s.height - y) as i64 * scale - 6 * k);
+            super::font::text(&y.to_string(), at, k, bg, &mut put);
+        }
+    }
+    let mut i = Image::<_, 4>::build(w, h).buf(out.into_boxed_slice());
+    if style.scale != 1.0 {
+        let (w, h) = (
+            ((w as f32 * style.scale).round() as u32).max(1),
+            ((h as f32 * style.scale).round() as u32).max(1),
+        );
+        i = i.scale::<fimg::scale::Nearest>(w, h);
+    }
+    super::png(Image::build(i.width(), i.height()).buf(i.take_buffer().into_vec()))
+}
+
 pub async fn from(m: (&str, &[Attachment])) -> Result<Option<Schem>> {
     match from_msg(m.0) {
         x @ Ok(Some(_)) => Ok(x?),
@@ -185,6 +454,14 @@ pub async fn from(m: (&str, &[Attachment])) -> Result<Option<Schem>> {
     }
 }
 
+/// Every schematic in a message, base64 first, then attachments.
+pub async fn all(m: (&str, &[Attachment])) -> Result<Vec<Schem>> {
+    let mut v = all_msg(m.0);
+    v.extend(all_attachments(m.1).await?);
+    v.truncate(MAX);
+    Ok(v)
+}
+
 pub fn from_msg(msg: &str) -> Result<Option<Schem>, R64Error> {
     RE.captures_iter(msg)
         .map(|x| x.get(0).unwrap().as_str())
@@ -193,11 +470,33 @@ pub fn from_msg(msg: &str) -> Result<Option<Schem>, R64Error> {
         .transpose()
 }
 
+/// Every schematic in the message, skipping any that dont decode.
+pub fn all_msg(msg: &str) -> Vec<Schem> {
+    RE.captures_iter(msg)
+        .map(|x| x.get(0).unwrap().as_str())
+        .filter(|x| x.starts_with("bXNjaA"))
+        .filter_map(|x| from_b64(x).ok())
+        .take(MAX)
+        .collect()
+}
+
+/// For commands that take a schematic as either base64 or a msch.
+pub async fn from_either(b64: Option<String>, msch: Option<Attachment>) -> Result<Option<Schem>> {
+    if let Some(s) = b64 {
+        return Ok(from_b64(&s).ok());
+    }
+    match msch {
+        Some(a) => from_attachments(std::slice::from_ref(&a)).await,
+        None => Ok(None),
+    }
+}
+
 pub fn from_b64(schem_text: &str) -> std::result::Result<Schem, R64Error> {
     Schematic::deserialize_base64(schem_text).map(|schem| Schem { schem })
 }
 
-fn decode_tags(tags: &str) -> Vec<String> {
+/// Splits a `labels` tag (`["a","b"]`) into its labels, or [`None`] if its malformed.
+fn lex_tags(tags: &str) -> Option<Vec<&str>> {
     #[derive(logos::Logos, PartialEq, Debug)]
     #[logos(skip r"[\s\n,]+")]
     enum Tokens<'s> {
@@ -211,16 +510,34 @@ fn decode_tags(tags: &str) -> Vec<String> {
     }
     let mut lexer = Tokens::lexer(tags);
     let mut t = Vec::new();
-    let mut next = || lexer.find_map(|x| x.ok());
-    assert_eq!(next().unwrap(), Tokens::Open);
-    while let Some(Tokens::String(x)) = next() {
-        let x = match x.trim() {
+    let mut next = || lexer.next().map(|x| x.ok());
+    if next()?? != Tokens::Open {
+        return None;
+    }
+    loop {
+        match next()?? {
+            Tokens::String(x) => t.push(x.trim()),
+            Tokens::Close => break,
+            Tokens::Open => return None,
+        }
+    }
+    lexer.next().is_none().then_some(t)
+}
+
+pub fn valid_tags(tags: &str) -> bool {
+    lex_tags(tags).is_some()
+}
+
+fn decode_tags(tags: &str) -> Vec<String> {
+    // an unclosed list shouldnt take the whole embed down with it
+    lex_tags(tags)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|x| match x {
             super::repos::SRP => "<:serpulo:1395767515950612593>",
             super::repos::ERE => "<:erekir:1395767762957369484>",
             _ => x,
-        };
-        t.push(emoji::mindustry::to_discord(x));
-    }
-    assert_eq!(lexer.next(), None);
-    t
+        })
+        .map(emoji::mindustry::to_discord)
+        .collect()
 }
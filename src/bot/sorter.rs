@@ -118,12 +118,16 @@ fn sort(
     )
 }
 
+/// Block names for each entry of `colors`, in the same order.
+const FLOORS: &[&str] = &include!("floors");
+
+/// Returns the preview and, for every pixel (top row first), its index into `colors`.
 fn map(
     mut x: Image<Box<[u8]>, 4>,
     scale_factor: Option<f32>,
     algorithm: Option<Scaling>,
     dithered: Option<Dithering>,
-) -> Image<Box<[u8]>, 4> {
+) -> (Image<Box<[u8]>, 4>, Box<[u32]>) {
     const PAL: &[[f32; 3]] = unsafe { include!("colors").as_chunks_unchecked::<3>() };
 
     if let Some(f) = scale_factor {
@@ -131,7 +135,77 @@ fn map(
     };
 
     let pal = PAL.iter().map(|&x| x.join(1.0)).collect::<Vec<_>>();
-    d(x.as_ref(), dithered, pal::new(&pal)).to().to_u8()
+    let mut quant = d(x.as_ref(), dithered, pal::new(&pal));
+    let indices = unsafe { quant.raw() }.buffer().into();
+    (quant.to().to_u8(), indices)
+}
+
+fn wall(name: &str) -> bool {
+    name.ends_with("-wall")
+        || name.starts_with("wall-ore-")
+        || matches!(name, "shrubs" | "spore-pine" | "dark-metal")
+}
+
+/// Builds a map where every pixel of `indices` is the matching floor, ore or wall, with a shard core at `core`.
+fn msav(
+    indices: &[u32],
+    (width, height): (usize, usize),
+    core: Option<(usize, usize)>,
+    name: String,
+) -> Result<Map> {
+    let get = |n: &str| block::BLOCK_REGISTRY.get(n).copied();
+    let stone = get("stone").unwrap();
+    let stone_wall = get("stone-wall").unwrap();
+    let mut m = Map::new(
+        width,
+        height,
+        [("name".to_string(), name)].into_iter().collect(),
+    );
+    for y in 0..height {
+        for x in 0..width {
+            let n = FLOORS[indices[(height - y - 1) * width + x] as usize];
+            let Some(b) = get(n) else { continue };
+            let t = &mut m.tiles[y * width + x];
+            match (n.starts_with("ore-") || n.starts_with("wall-ore-"), wall(n)) {
+                // ores on walls
+                (true, true) => {
+                    t.floor = stone;
+                    t.ore = Some(b);
+                    t.build = Some(data::map::Build::new(stone_wall));
+                }
+                (true, false) => {
+                    t.floor = stone;
+                    t.ore = Some(b);
+                }
+                (false, true) => {
+                    t.floor = stone;
+                    t.build = Some(data::map::Build::new(b));
+                }
+                (false, false) => t.floor = b,
+            }
+        }
+    }
+    if let Some((x, y)) = core {
+        let core = &block::CORE_SHARD;
+        let r = (core.get_size() as usize - 1) / 2;
+        if x < r || y < r || x + r >= width || y + r >= height {
+            anyhow::bail!("the core at ({x}, {y}) doesnt fit in a {width}×{height} map");
+        }
+        // clear the footprint, so the core isnt sitting in walls or deep water
+        for ty in y - r..=y + r {
+            for tx in x - r..=x + r {
+                let t = &mut m.tiles[ty * width + tx];
+                t.build = None;
+                if !wall(t.floor.name()) && t.floor.name().contains("water") {
+                    t.floor = stone;
+                }
+            }
+        }
+        let mut b = data::map::Build::new(core);
+        b.team = team::Team::SHARDED;
+        m.tiles[y * width + x].build = Some(b);
+    }
+    Ok(m)
 }
 
 #[poise::command(slash_command)]
@@ -188,7 +262,7 @@ pub async fn sorter(
 }
 
 #[poise::command(slash_command)]
-/// Create map representations of images.
+/// Create playable maps (msav) from images.
 pub async fn mapper(
     c: super::Context<'_>,
     #[description = "image: png, webp, jpg"] i: Attachment,
@@ -196,14 +270,30 @@ pub async fn mapper(
     #[description = "scaling algorithm, defaults to nearest"] algorithm: Option<Scaling>,
     #[description = "dithering algorithm, defaults to none (if you want the map to be playable, go with ordered)"]
     dithered: Option<Dithering>,
+    #[description = "place a core at: x, y (in tiles, from the bottom left)"] core: Option<String>,
 ) -> Result<()> {
+    use crate::emoji::named::*;
     super::log(&c);
+    let core = match core
+        .as_deref()
+        .map(|c| match super::map::numbers(c).as_deref() {
+            Some(&[x, y]) => Ok((x, y)),
+            _ => Err(format!("`{c}` is not a position: `x, y`")),
+        })
+        .transpose()
+    {
+        Ok(x) => x,
+        Err(e) => {
+            c.say(format!("{CANCEL} {e}")).await?;
+            return Ok(());
+        }
+    };
     c.defer().await?;
     let image = i.download().await?;
     match image::load_from_memory(&image) {
         Ok(x) => {
             let x = x.to_rgba8();
-            let preview = map(
+            let (preview, indices) = map(
                 Image::<_, 4>::build(x.width(), x.height())
                     .buf(x.into_vec())
                     .boxed(),
@@ -211,12 +301,37 @@ pub async fn mapper(
                 algorithm,
                 dithered,
             );
+            let mut h = std::hash::DefaultHasher::default();
+            std::hash::Hasher::write(&mut h, preview.bytes());
+            let h = std::hash::Hasher::finish(&h) as u32;
+            let size = (preview.width() as usize, preview.height() as usize);
+            let m = match msav(&indices, size, core, format!("mapper #{h:x}")) {
+                Ok(x) => x,
+                Err(e) => {
+                    c.say(format!("{CANCEL} {e}")).await?;
+                    return Ok(());
+                }
+            };
+            let mut buff = data::DataWrite::default();
+            m.serialize(&mut buff)?;
+            let buff = buff.consume();
+            // read it back, so that what gets shown is what the game will load
+            let then = std::time::Instant::now();
+            let m = match Map::deserialize(&mut data::DataRead::new(&buff)) {
+                Ok(x) => x,
+                Err(e) => anyhow::bail!("generated map doesnt load: {e:?}"),
+            };
+            let (png, embed) =
+                super::map::embed(m, then.elapsed(), super::map::Options::default()).await?;
             let mut preview_png = Vec::with_capacity(1 << 11);
             fimg::WritePng::write(&preview, &mut preview_png).unwrap();
             poise::send_reply(
                 c,
                 poise::CreateReply::default()
-                    .attachment(CreateAttachment::bytes(preview_png, "preview.png")),
+                    .attachment(CreateAttachment::bytes(preview_png, "preview.png"))
+                    .attachment(png)
+                    .attachment(CreateAttachment::bytes(buff, format!("mapper{h:x}.msav")))
+                    .embed(embed),
             )
             .await?;
         }
@@ -0,0 +1,146 @@
+use super::schematic::{self, Schem};
+use super::Context;
+use anyhow::Result;
+use poise::ChoiceParameter;
+use poise::serenity_prelude::*;
+
+#[derive(ChoiceParameter, Copy, Clone)]
+pub enum Transform {
+    #[name = "rotate 90° clockwise"]
+    Rotate90,
+    #[name = "rotate 180°"]
+    Rotate180,
+    #[name = "rotate 270° clockwise"]
+    Rotate270,
+    /// left <-> right
+    #[name = "mirror horizontally"]
+    MirrorH,
+    /// top <-> bottom
+    #[name = "mirror vertically"]
+    MirrorV,
+}
+
+/// Applies `t`. Block rotations and relative configs (bridge, mass driver, node links, etc) are fixed up by mindus.
+pub fn apply(s: &mut mindus::Schematic, t: Transform) -> Result<()> {
+    match t {
+        Transform::Rotate90 => s.rotate(true)?,
+        Transform::Rotate180 => s.rotate_180()?,
+        Transform::Rotate270 => s.rotate(false)?,
+        Transform::MirrorH => s.mirror(true, false),
+        Transform::MirrorV => s.mirror(false, true),
+    };
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    install_context = "User|Guild",
+    interaction_context = "Guild|BotDm|PrivateChannel"
+)]
+/// Rotate or mirror a schematic.
+pub async fn transform(
+    c: Context<'_>,
+    #[description = "what to do"] how: Transform,
+    #[description = "schematic, base64"] s: Option<String>,
+    #[description = "schematic, msch"] file: Option<Attachment>,
+) -> Result<()> {
+    super::log(&c);
+    c.defer().await?;
+    let Some(Schem { schem: mut s }) = schematic::from_either(s, file).await? else {
+        c.reply("no schem!").await?;
+        return Ok(());
+    };
+    apply(&mut s, how)?;
+    poise::send_reply(
+        c,
+        schematic::output(Schem { schem: s }, &c.author().name, &c.author().face()).await?,
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Transform, apply};
+    use mindus::Schematic;
+    use mindus::block::{self, Rotation};
+    use mindus::data::dynamic::DynData;
+
+    /// A conveyor and a pair of linked bridges, none of it symmetric.
+    fn sample() -> Schematic {
+        let mut s = Schematic::new(3, 2);
+        s.set(
+            0,
+            0,
+            &block::BRIDGE_CONVEYOR,
+            DynData::Point2(2, 0),
+            Rotation::Up,
+        )
+        .unwrap();
+        s.set(2, 0, &block::BRIDGE_CONVEYOR, DynData::Empty, Rotation::Up)
+            .unwrap();
+        s.set(0, 1, &block::CONVEYOR, DynData::Empty, Rotation::Right)
+            .unwrap();
+        s
+    }
+
+    fn same(a: &Schematic, b: &Schematic) -> bool {
+        super::super::dupes::hashes(a).0 == super::super::dupes::hashes(b).0
+    }
+
+    /// lint knows where a bridge's link lands.
+    fn bridges_still_linked(s: &Schematic) {
+        let bad = super::super::lint::check(s)
+            .into_iter()
+            .filter(|f| f.what.starts_with("bridge"))
+            .count();
+        assert_eq!(bad, 0, "a bridge lost its link");
+    }
+
+    #[test]
+    fn four_quarter_turns_are_nothing() {
+        let mut s = sample();
+        for _ in 0..4 {
+            apply(&mut s, Transform::Rotate90).unwrap();
+            bridges_still_linked(&s);
+        }
+        assert!(same(&s, &sample()));
+    }
+
+    #[test]
+    fn turns_undo_each_other() {
+        let mut s = sample();
+        apply(&mut s, Transform::Rotate90).unwrap();
+        assert_eq!((s.width, s.height), (2, 3));
+        apply(&mut s, Transform::Rotate270).unwrap();
+        assert!(same(&s, &sample()));
+    }
+
+    #[test]
+    fn half_turn_is_two_quarter_turns() {
+        let (mut a, mut b) = (sample(), sample());
+        apply(&mut a, Transform::Rotate180).unwrap();
+        apply(&mut b, Transform::Rotate90).unwrap();
+        apply(&mut b, Transform::Rotate90).unwrap();
+        assert!(same(&a, &b));
+        bridges_still_linked(&a);
+    }
+
+    #[test]
+    fn mirrors_flip_rotations_and_links() {
+        for t in [Transform::MirrorH, Transform::MirrorV] {
+            let mut s = sample();
+            apply(&mut s, t).unwrap();
+            bridges_still_linked(&s);
+            apply(&mut s, t).unwrap();
+            assert!(same(&s, &sample()));
+        }
+        let mut s = sample();
+        apply(&mut s, Transform::MirrorH).unwrap();
+        let (_, p) = s
+            .block_iter()
+            .find(|(_, p)| p.block.name() == "conveyor")
+            .unwrap();
+        assert_eq!(p.rot, Rotation::Left);
+    }
+}
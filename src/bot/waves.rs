@@ -0,0 +1,334 @@
+use super::{Context, SUCCESS, strip_colors};
+use crate::emoji;
+use anyhow::Result;
+use charts_rs::{Series, THEME_GRAFANA};
+use poise::{CreateReply, serenity_prelude::*};
+use serde_json::Value;
+use std::fmt::Write;
+
+macro_rules! units {
+    ($($u:ident => $health:literal),+ $(,)?) => { paste::paste! {
+        fn icon(unit: &str) -> &'static str {
+            use emoji::to_mindustry::named::*;
+            match unit {
+                $(stringify!([< $u:lower >]) => $u,)+
+                _ => "",
+            }
+        }
+        fn health(unit: &str) -> f32 {
+            match unit {
+                $(stringify!([< $u:lower >]) => $health as f32,)+
+                _ => 0.0,
+            }
+        }
+    } };
+}
+
+units! {
+    DAGGER => 130, MACE => 550, FORTRESS => 900, SCEPTER => 9000, REIGN => 24000,
+    NOVA => 120, PULSAR => 320, QUASAR => 640, VELA => 8200, CORVUS => 18000,
+    CRAWLER => 200, ATRAX => 460, SPIROCT => 760, ARKYID => 9000, TOXOPID => 22000,
+    FLARE => 70, HORIZON => 340, ZENITH => 700, ANTUMBRA => 7200, ECLIPSE => 22000,
+    MONO => 100, POLY => 400, MEGA => 460, QUAD => 6000, OCT => 24000,
+    RISSO => 280, MINKE => 600, BRYDE => 910, SEI => 11000, OMURA => 22000,
+    RETUSA => 270, OXYNOE => 560, CYERCE => 870, AEGIRES => 12000, NAVANAX => 20000,
+    STELL => 850, LOCUS => 2100, PRECEPT => 5000, VANQUISH => 11000, CONQUER => 22000,
+    MERUI => 680, CLEROI => 1100, ANTHICUS => 2900, TECTA => 7300, COLLARIS => 18000,
+    ELUDE => 600, AVERT => 1100, OBVIATE => 2300, QUELL => 6000, DISRUPT => 12000,
+}
+
+/// Mirrors mindustry's `SpawnGroup`.
+pub struct Group {
+    pub unit: String,
+    begin: i64,
+    end: i64,
+    spacing: i64,
+    max: i64,
+    scaling: f64,
+    amount: i64,
+    shields: f64,
+    shield_scaling: f64,
+    pub effect: Option<String>,
+}
+
+impl Group {
+    fn new(v: &Value) -> Option<Self> {
+        let i = |k, d| v.get(k).and_then(Value::as_i64).unwrap_or(d);
+        let f = |k, d| v.get(k).and_then(Value::as_f64).unwrap_or(d);
+        Some(Self {
+            unit: v.get("type")?.as_str()?.to_string(),
+            begin: i("begin", 0),
+            end: i("end", i32::MAX as i64),
+            spacing: i("spacing", 1).max(1),
+            max: i("max", 100),
+            scaling: f("unitScaling", f32::MAX as f64),
+            amount: i("unitAmount", 1),
+            shields: f("shields", 0.0),
+            shield_scaling: f("shieldScaling", 0.0),
+            effect: v
+                .get("effect")
+                .and_then(Value::as_str)
+                .filter(|x| *x != "none")
+                .map(str::to_string),
+        })
+    }
+
+    /// units spawned on `wave`, 0 indexed
+    pub fn spawned(&self, wave: i64) -> i64 {
+        if wave < self.begin || wave > self.end || (wave - self.begin) % self.spacing != 0 {
+            return 0;
+        }
+        (self.amount + (((wave - self.begin) / self.spacing) as f64 / self.scaling) as i64)
+            .min(self.max)
+    }
+
+    pub fn shield(&self, wave: i64) -> f64 {
+        (self.shields + self.shield_scaling * (wave - self.begin) as f64).max(0.0)
+    }
+}
+
+/// Mindustry writes rules as "minimal" json: keys and strings are left unquoted unless they need quotes.
+/// This quotes every bare word that isn't a number, `true`, `false` or `null`, and every bare key.
+pub(super) fn json(rules: &str) -> Option<Value> {
+    serde_json::from_str(rules)
+        .ok()
+        .or_else(|| serde_json::from_str(&quote(rules)).ok())
+}
+
+fn quote(rules: &str) -> String {
+    let mut out = String::with_capacity(rules.len() + rules.len() / 4);
+    // true for objects, to tell keys from values
+    let mut nest = vec![];
+    let mut key = false;
+    let mut chars = rules.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '{' | '[' => {
+                nest.push(c == '{');
+                key = c == '{';
+                out.push(c);
+            }
+            '}' | ']' => {
+                nest.pop();
+                key = false;
+                out.push(c);
+            }
+            ',' => {
+                key = nest.last() == Some(&true);
+                out.push(c);
+            }
+            ':' => {
+                key = false;
+                out.push(c);
+            }
+            c if c.is_whitespace() => out.push(c),
+            '"' => {
+                out.push('"');
+                let mut escaped = false;
+                for (_, c) in chars.by_ref() {
+                    out.push(c);
+                    match c {
+                        _ if escaped => escaped = false,
+                        '\\' => escaped = true,
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {
+                // keys run up to the colon, values up to the next `,`, `}` or `]`; either stops at a line end.
+                let mut end = rules.len();
+                while let Some(&(j, c)) = chars.peek() {
+                    let stop = if key {
+                        c == ':'
+                    } else {
+                        matches!(c, ',' | '}' | ']')
+                    };
+                    if stop || matches!(c, '\n' | '\r') {
+                        end = j;
+                        break;
+                    }
+                    chars.next();
+                }
+                let word = rules[i..end].trim_end();
+                let literal = matches!(word, "true" | "false" | "null")
+                    || serde_json::from_str::<serde_json::Number>(word).is_ok();
+                if literal && !key {
+                    out.push_str(word);
+                } else {
+                    out.push_str(&serde_json::to_string(word).unwrap());
+                }
+                out.push_str(&rules[i + word.len()..end]);
+            }
+        }
+    }
+    out
+}
+
+/// The rules' spawn groups, if they set any.
+pub fn groups(rules: &Value) -> Option<Vec<Group>> {
+    rules
+        .get("spawns")?
+        .as_array()
+        .map(|x| x.iter().filter_map(Group::new).collect())
+}
+
+#[poise::command(
+    slash_command,
+    install_context = "User|Guild",
+    interaction_context = "Guild|BotDm|PrivateChannel"
+)]
+/// Preview a map's waves.
+pub async fn waves(
+    c: Context<'_>,
+    #[description = "map, msav"] map: Attachment,
+    #[description = "number of waves (1–50), defaults to 20"] count: Option<u8>,
+) -> Result<()> {
+    use emoji::named::*;
+    super::log(&c);
+    c.defer().await?;
+    let m = match super::map::download(&map).await? {
+        (Err(e), _) => return Ok(drop(c.say(super::map::string(e)).await?)),
+        (Ok((m, _)), _) => m,
+    };
+    let name = strip_colors(m.tags.get("name").or(m.tags.get("mapname")).unwrap());
+    let rules = match m.tags.get("rules").map(|x| json(x)) {
+        Some(Some(x)) => Some(x),
+        Some(None) => {
+            c.say(format!("{CANCEL} couldn't read {name}'s rules"))
+                .await?;
+            return Ok(());
+        }
+        None => None,
+    };
+    let Some(groups) = rules.as_ref().and_then(groups) else {
+        c.say(format!("{CANCEL} {name} uses the default waves"))
+            .await?;
+        return Ok(());
+    };
+    let n = count.unwrap_or(20).clamp(1, 50) as i64;
+    let mut list = String::new();
+    let mut totals = vec![];
+    for wave in 0..n {
+        let mut line = String::new();
+        let mut total = 0.0;
+        for g in &groups {
+            let k = g.spawned(wave);
+            if k == 0 {
+                continue;
+            }
+            let shield = g.shield(wave);
+            total += (health(&g.unit) + shield as f32) * k as f32;
+            let icon = emoji::mindustry::to_discord(icon(&g.unit));
+            let icon = if icon.is_empty() {
+                format!("`{}`", g.unit)
+            } else {
+                icon
+            };
+            write!(line, "{k}×{icon}").unwrap();
+            if shield > 0.0 {
+                write!(line, " +{shield:.0}").unwrap();
+            }
+            if let Some(e) = &g.effect {
+                write!(line, " ({e})").unwrap();
+            }
+            line.push(' ');
+        }
+        let line = format!(
+            "**{}**: {}\n",
+            wave + 1,
+            if line.is_empty() { "—" } else { &line }
+        );
+        // the chart stops where the list does
+        if list.len() + line.len() > 4000 {
+            break;
+        }
+        list.push_str(&line);
+        totals.push(total);
+    }
+    let shown = totals.len();
+    let mut ch = charts_rs::BarChart::new_with_theme(
+        vec![Series::new("enemy health".into(), totals)],
+        (1..=shown).map(|x| x.to_string()).collect(),
+        THEME_GRAFANA,
+    );
+    ch.title_text = "total enemy health + shields".into();
+    ch.width = 800.0;
+    ch.height = 300.0;
+    let chart = charts_rs::svg_to_webp(&ch.svg().unwrap()).unwrap();
+    poise::send_reply(
+        c,
+        CreateReply::default()
+            .attachment(CreateAttachment::bytes(chart, "waves.webp"))
+            .embed(
+                CreateEmbed::new()
+                    .title(format!("waves of {name}"))
+                    .description(list)
+                    .footer(CreateEmbedFooter::new("count × unit +shields (effect)"))
+                    .image("attachment://waves.webp")
+                    .color(SUCCESS),
+            ),
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn already_json() {
+        assert_eq!(json(r#"{"a":1}"#), Some(json!({"a": 1})));
+    }
+
+    #[test]
+    fn bare_keys_and_words() {
+        assert_eq!(
+            json("{waves:true,winWave:30,spawns:[{type:dagger,effect:none,end:10}]}"),
+            Some(
+                json!({"waves": true, "winWave": 30, "spawns": [{"type": "dagger", "effect": "none", "end": 10}]})
+            )
+        );
+    }
+
+    #[test]
+    fn awkward_words() {
+        assert_eq!(
+            json("{color:0ff,name:my map: hard,x:-1.5e3,n:null,team:{id:2}}"),
+            Some(
+                json!({"color": "0ff", "name": "my map: hard", "x": -1.5e3, "n": null, "team": {"id": 2}})
+            )
+        );
+    }
+
+    #[test]
+    fn quoted_strings_are_left_alone() {
+        assert_eq!(
+            json(r#"{tags:["a,b","c\"d"],0:ok}"#),
+            Some(json!({"tags": ["a,b", "c\"d"], "0": "ok"}))
+        );
+    }
+
+    #[test]
+    fn multiline() {
+        assert_eq!(
+            json("{\n  waves: true,\n  name: x y\n}"),
+            Some(json!({"waves": true, "name": "x y"}))
+        );
+    }
+
+    #[test]
+    fn groups_need_spawns() {
+        let r = json("{waves:true}").unwrap();
+        assert!(groups(&r).is_none());
+        let r = json("{spawns:[{type:flare,unitAmount:2,unitScaling:2,begin:1,max:3}]}").unwrap();
+        let g = groups(&r).unwrap();
+        assert_eq!(g[0].unit, "flare");
+        assert_eq!(
+            (0..8).map(|w| g[0].spawned(w)).collect::<Vec<_>>(),
+            [0, 2, 2, 3, 3, 3, 3, 3]
+        );
+    }
+}
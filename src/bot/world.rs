@@ -0,0 +1,513 @@
+// a pretend world for eval: lemu knows nothing of ticks, switches or sensors,
+// so programs get rewritten to read them from variables and literals instead.
+use super::pack::Display;
+use poise::{ChoiceParameter, KeyValueArgs};
+use serde_json::Value;
+use std::fmt::Write;
+
+const CELL: usize = 64;
+const BANK: usize = 512;
+/// Cells, and banks, an executor may have.
+const MEMORY: usize = 32;
+/// Displays an executor may have, and embeds a reply may have.
+const DISPLAYS: usize = 10;
+
+#[derive(Default, Clone)]
+pub struct World {
+    /// (cellN, contents)
+    pub cells: Vec<(usize, Vec<f64>)>,
+    /// (bankN, contents)
+    pub banks: Vec<(usize, Vec<f64>)>,
+    /// (link name, enabled)
+    pub switches: Vec<(String, bool)>,
+    /// (link name, [(@property, value)])
+    pub sensors: Vec<(String, Vec<(String, f64)>)>,
+}
+
+#[derive(Default, Clone)]
+pub struct Env {
+    /// `@tick`
+    pub tick: Option<f64>,
+    /// `@time`, in ms
+    pub time: Option<f64>,
+    /// makes `op rand` deterministic
+    pub seed: Option<u64>,
+    /// bound to `display1..N`; one large display when empty
+    pub displays: Vec<Display>,
+    /// capture the displays every iteration
+    pub animate: bool,
+    pub dump: Dump,
+    pub world: World,
+}
+
+/// What to show once the program is done, besides its output.
+#[derive(Default, Clone, Copy)]
+pub struct Dump {
+    pub vars: bool,
+    pub memory: bool,
+}
+
+/// `vars,memory`
+pub fn dump(s: &str) -> Result<Dump, String> {
+    let mut d = Dump::default();
+    for x in s.split(',').map(str::trim).filter(|x| !x.is_empty()) {
+        match x {
+            "vars" | "variables" => d.vars = true,
+            "memory" | "mem" => d.memory = true,
+            x => return Err(format!("`{x}` is not something to dump (vars or memory)")),
+        }
+    }
+    Ok(d)
+}
+
+/// `large,small,small`
+pub fn displays(s: &str) -> Result<Vec<Display>, String> {
+    let v = s
+        .split(',')
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(|x| {
+            Display::from_name(x)
+                .ok_or_else(|| format!("`{x}` is not a display size (small or large)"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if v.len() > DISPLAYS {
+        return Err(format!("at most {DISPLAYS} displays"));
+    }
+    Ok(v)
+}
+
+fn memory(v: &Value, prefix: &str, size: usize) -> Result<Vec<(usize, Vec<f64>)>, String> {
+    let Some(o) = v.as_object() else {
+        return Ok(vec![]);
+    };
+    o.iter()
+        .map(|(k, v)| {
+            let n = k
+                .strip_prefix(prefix)
+                .and_then(|x| x.parse::<usize>().ok())
+                .ok_or_else(|| format!("`{k}` is not a {prefix} (eg `{prefix}1`)"))?;
+            if !(1..=MEMORY).contains(&n) {
+                return Err(format!(
+                    "`{k}` is out of range ({prefix}1 to {prefix}{MEMORY})"
+                ));
+            }
+            let values = v
+                .as_array()
+                .ok_or_else(|| format!("`{k}` should be a list of numbers"))?
+                .iter()
+                .map(|x| {
+                    x.as_f64()
+                        .or(x.as_bool().map(|x| x as u8 as f64))
+                        .unwrap_or(0.0)
+                })
+                .take(size)
+                .collect();
+            Ok((n, values))
+        })
+        .collect()
+}
+
+impl World {
+    /// ```json
+    /// {
+    ///   "cells": { "cell1": [1, 2, 3] },
+    ///   "banks": { "bank1": [4] },
+    ///   "switches": { "switch1": true },
+    ///   "sensors": { "container1": { "@copper": 120 } }
+    /// }
+    /// ```
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let v: Value = serde_json::from_str(json).map_err(|e| format!("bad world json: {e}"))?;
+        Ok(Self {
+            cells: memory(&v["cells"], "cell", CELL)?,
+            banks: memory(&v["banks"], "bank", BANK)?,
+            switches: v["switches"]
+                .as_object()
+                .into_iter()
+                .flatten()
+                .map(|(k, v)| (k.clone(), v.as_bool().unwrap_or(v.as_f64() != Some(0.0))))
+                .collect(),
+            sensors: v["sensors"]
+                .as_object()
+                .into_iter()
+                .flatten()
+                .map(|(k, v)| {
+                    (
+                        k.clone(),
+                        v.as_object()
+                            .into_iter()
+                            .flatten()
+                            .map(|(p, x)| {
+                                let p = if p.starts_with('@') {
+                                    p.clone()
+                                } else {
+                                    format!("@{p}")
+                                };
+                                (
+                                    p,
+                                    x.as_f64()
+                                        .or(x.as_bool().map(|x| x as u8 as f64))
+                                        .unwrap_or(0.0),
+                                )
+                            })
+                            .collect(),
+                    )
+                })
+                .collect(),
+        })
+    }
+
+    /// The highest cell and bank the world fills in.
+    pub fn memory(&self) -> (usize, usize) {
+        let max = |x: &[(usize, Vec<f64>)]| x.iter().map(|(n, _)| *n).max().unwrap_or(0);
+        (max(&self.cells), max(&self.banks))
+    }
+}
+
+impl Env {
+    /// From `tick=`, `time=`, `seed=`, `displays=`, `animate=` and `dump=`, and the world block.
+    pub fn new(kv: &KeyValueArgs, world: Option<&str>) -> Result<Self, String> {
+        fn get<T: std::str::FromStr>(kv: &KeyValueArgs, k: &str) -> Result<Option<T>, String> {
+            kv.get(k)
+                .map(|v| v.parse().map_err(|_| format!("`{k}={v}` is not a number")))
+                .transpose()
+        }
+        Ok(Self {
+            tick: get(kv, "tick")?,
+            time: get(kv, "time")?,
+            seed: get(kv, "seed")?,
+            dump: kv.get("dump").map(dump).transpose()?.unwrap_or_default(),
+            animate: kv.get("animate").is_some_and(|x| x == "true"),
+            displays: kv
+                .get("displays")
+                .map(displays)
+                .transpose()?
+                .unwrap_or_default(),
+            world: world.map(World::parse).transpose()?.unwrap_or_default(),
+        })
+    }
+
+    /// How many cells and banks the executor needs: the world's, and any `code` names.
+    pub fn memory(&self, code: &str) -> (usize, usize) {
+        let (mut cells, mut banks) = self.world.memory();
+        for t in code.lines().flat_map(tokens) {
+            // past that, it's as if nothing were linked
+            let n = |p| {
+                t.strip_prefix(p)
+                    .and_then(|x| x.parse::<usize>().ok())
+                    .filter(|&x| x <= MEMORY)
+            };
+            if let Some(n) = n("cell") {
+                cells = cells.max(n);
+            } else if let Some(n) = n("bank") {
+                banks = banks.max(n);
+            }
+        }
+        (cells, banks)
+    }
+
+    /// Rewrites `code` to run in this environment.
+    ///
+    /// Every instruction keeps its index, so numeric jumps and `@counter` arithmetic land where they did: the setup,
+    /// and any instruction that grows, is moved past the end and jumped to and back.
+    pub fn apply(&self, code: &str) -> Result<String, String> {
+        let mut pre = vec![];
+        for (n, values) in &self.world.cells {
+            for (i, v) in values.iter().enumerate() {
+                pre.push(format!("write {v} cell{n} {i}"));
+            }
+        }
+        for (n, values) in &self.world.banks {
+            for (i, v) in values.iter().enumerate() {
+                pre.push(format!("write {v} bank{n} {i}"));
+            }
+        }
+        for (name, on) in &self.world.switches {
+            pre.push(format!("set __{name} {}", *on as u8));
+        }
+        if let Some(s) = self.seed {
+            // minstd wants 1..2^31-1
+            pre.push(format!("set __seed {}", s % 2147483646 + 1));
+        }
+        let lines = code
+            .lines()
+            .map(tokens)
+            .filter(|x| !x.is_empty())
+            .collect::<Vec<_>>();
+        // labels stay labels, and take no index
+        let label = |t: &[String]| t.len() == 1 && t[0].ends_with(':');
+        if lines.iter().all(|t| label(t))
+            || (pre.is_empty()
+                && self.tick.is_none()
+                && self.time.is_none()
+                && self.world.sensors.is_empty())
+        {
+            return Ok(code.to_string());
+        }
+        let mut body = vec![];
+        // past the `end` that stops the program running into it. jumps there and back go by label,
+        // as numbers would have to agree with lemu about what counts as an instruction
+        let mut tail = vec![];
+        let mut i = 0;
+        for t in lines {
+            if label(&t) {
+                body.push(t.join(" "));
+                continue;
+            }
+            let first = i == 0 && !pre.is_empty();
+            if first && t.iter().any(|x| x == "@counter") {
+                return Err(
+                    "the first instruction can't use `@counter` with a world or seed, as the setup runs in its place"
+                        .to_string(),
+                );
+            }
+            let x = self
+                .instruction(t)
+                .into_iter()
+                .map(|t| t.join(" "))
+                .collect::<Vec<_>>();
+            if first || x.len() > 1 {
+                body.push(format!("jump __out{i} always"));
+                body.push(format!("__back{i}:"));
+                tail.push(format!("__out{i}:"));
+                if first {
+                    // once, not every time the program loops
+                    tail.push("jump __setup equal __world 1".to_string());
+                    tail.append(&mut pre);
+                    tail.push("set __world 1".to_string());
+                    tail.push("__setup:".to_string());
+                }
+                tail.extend(x);
+                tail.push(format!("jump __back{i} always"));
+            } else {
+                body.extend(x);
+            }
+            i += 1;
+        }
+        body.push("end".to_string());
+        body.extend(tail);
+        Ok(body.join("\n"))
+    }
+
+    fn instruction(&self, mut t: Vec<String>) -> Vec<Vec<String>> {
+        let s = |x: &str| x.to_string();
+        for x in &mut t[1..] {
+            let v = match &**x {
+                "@tick" => self.tick,
+                "@time" => self.time,
+                "@second" => self.time.map(|x| x / 1000.0),
+                _ => None,
+            };
+            if let Some(v) = v {
+                *x = v.to_string();
+            }
+        }
+        let words = |x: String| x.split(' ').map(s).collect::<Vec<_>>();
+        let r = match &*t.iter().map(|x| &**x).collect::<Vec<_>>() {
+            ["sensor", out, link, "@enabled", ..]
+                if self.world.switches.iter().any(|(n, _)| n == link) =>
+            {
+                Some(vec![words(format!("set {out} __{link}"))])
+            }
+            ["sensor", out, link, prop, ..]
+                if let Some((_, props)) = self.world.sensors.iter().find(|(n, _)| n == link) =>
+            {
+                let v = props
+                    .iter()
+                    .find(|(p, _)| p == prop)
+                    .map_or("null".to_string(), |(_, v)| v.to_string());
+                Some(vec![words(format!("set {out} {v}"))])
+            }
+            ["control", "enabled", link, v, ..]
+                if self.world.switches.iter().any(|(n, _)| n == link) =>
+            {
+                Some(vec![words(format!("set __{link} {v}"))])
+            }
+            // minstd, so the same seed gives the same numbers. the products stay well inside a double's 53 bits
+            ["op", "rand", out, max, ..] if self.seed.is_some() => Some(
+                [
+                    s("op mul __seed __seed 48271"),
+                    s("op mod __seed __seed 2147483647"),
+                    format!("op div {out} __seed 2147483647"),
+                    format!("op mul {out} {out} {max}"),
+                ]
+                .into_iter()
+                .map(words)
+                .collect(),
+            ),
+            _ => None,
+        };
+        r.unwrap_or_else(|| vec![t])
+    }
+
+    /// For the reply, so it's clear what was pretended.
+    pub fn describe(&self) -> Option<String> {
+        let mut s = String::new();
+        if let Some(t) = self.tick {
+            write!(s, "@tick={t} ").unwrap();
+        }
+        if let Some(t) = self.time {
+            write!(s, "@time={t} ").unwrap();
+        }
+        if let Some(x) = self.seed {
+            write!(s, "seed={x} ").unwrap();
+        }
+        if !self.displays.is_empty() {
+            write!(
+                s,
+                "displays={} ",
+                self.displays
+                    .iter()
+                    .map(|x| x.name())
+                    .intersperse(",")
+                    .collect::<String>()
+            )
+            .unwrap();
+        }
+        let w = &self.world;
+        let n = w.cells.len() + w.banks.len() + w.switches.len() + w.sensors.len();
+        if n != 0 {
+            write!(s, "world: {n} buildings").unwrap();
+        }
+        (!s.is_empty()).then(|| s.trim_end().to_string())
+    }
+}
+
+/// Splits a line of mlog on whitespace, keeping strings whole and dropping comments.
+pub(super) fn tokens(l: &str) -> Vec<String> {
+    let mut v = vec![];
+    let mut cur = String::new();
+    let mut string = false;
+    for c in l.chars() {
+        match c {
+            '"' => {
+                string = !string;
+                cur.push(c);
+            }
+            '#' if !string => break,
+            c if c.is_whitespace() && !string => {
+                if !cur.is_empty() {
+                    v.push(std::mem::take(&mut cur));
+                }
+            }
+            c => cur.push(c),
+        }
+    }
+    if !cur.is_empty() {
+        v.push(cur);
+    }
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The instructions, without labels.
+    fn instructions(code: &str) -> Vec<String> {
+        code.lines()
+            .filter(|l| !l.ends_with(':'))
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn nothing_to_pretend() {
+        let code = "print 1\n\nend";
+        assert_eq!(Env::default().apply(code).unwrap(), code);
+    }
+
+    #[test]
+    fn replaced_in_place() {
+        let env = Env {
+            tick: Some(5.0),
+            ..Default::default()
+        };
+        assert_eq!(
+            env.apply("set t @tick # now\nprint t").unwrap(),
+            "set t 5\nprint t\nend"
+        );
+    }
+
+    #[test]
+    fn instructions_keep_their_index() {
+        let env = Env {
+            seed: Some(0),
+            ..Default::default()
+        };
+        let code = "op rand r 10\nset @counter 3\nprint r\nloop:\nop rand r 10\njump loop always";
+        let out = env.apply(code).unwrap();
+        assert_eq!(
+            instructions(&out)[..6],
+            [
+                "jump __out0 always",
+                "set @counter 3",
+                "print r",
+                "jump __out3 always",
+                "jump loop always",
+                "end",
+            ]
+        );
+        let lines = out.lines().collect::<Vec<_>>();
+        let setup = lines.iter().position(|&l| l == "__out0:").unwrap();
+        assert_eq!(
+            lines[setup..][..9],
+            [
+                "__out0:",
+                "jump __setup equal __world 1",
+                "set __seed 1",
+                "set __world 1",
+                "__setup:",
+                "op mul __seed __seed 48271",
+                "op mod __seed __seed 2147483647",
+                "op div r __seed 2147483647",
+                "op mul r r 10",
+            ]
+        );
+        assert_eq!(lines[setup + 9], "jump __back0 always");
+        // and back to right after
+        let back = lines.iter().position(|&l| l == "__back3:").unwrap();
+        assert_eq!(lines[back - 1], "jump __out3 always");
+        assert_eq!(lines[back + 1], "jump loop always");
+    }
+
+    #[test]
+    fn seed_stays_in_range() {
+        let seed = |s| {
+            Env {
+                seed: Some(s),
+                ..Default::default()
+            }
+            .apply("print 1")
+            .unwrap()
+            .lines()
+            .find_map(|l| l.strip_prefix("set __seed ").map(str::to_string))
+            .unwrap()
+        };
+        assert_eq!(seed(0), "1");
+        assert_eq!(seed(2147483645), "2147483646");
+        assert_eq!(seed(2147483646), "1");
+        assert_eq!(seed(u64::MAX), (u64::MAX % 2147483646 + 1).to_string());
+    }
+
+    #[test]
+    fn counter_first_is_refused() {
+        let seeded = Env {
+            seed: Some(1),
+            ..Default::default()
+        };
+        assert!(seeded.apply("start:\nset @counter 2\nend").is_err());
+        assert!(seeded.apply("print 1\nset @counter 0").is_ok());
+        let timed = Env {
+            time: Some(1.0),
+            ..Default::default()
+        };
+        assert!(timed.apply("set @counter 2\nend").is_ok());
+    }
+
+    #[test]
+    fn strings_stay_whole() {
+        assert_eq!(tokens(r#"print "a # b"  # c"#), ["print", "\"a # b\""]);
+    }
+}